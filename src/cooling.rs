@@ -0,0 +1,60 @@
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CoolingSchedule {
+    // multiplier / progress
+    Hyperbolic { multiplier: f32 },
+    // geometric interpolation from start to end
+    Exponential { start: f32, end: f32 },
+    // end + (start - end) * (1 - progress)^power, linear when power is 1
+    Polynomial { start: f32, end: f32, power: f32 },
+}
+
+impl CoolingSchedule {
+    // `progress` runs from 0 at the start of solving (or of a reheat cycle) to 1
+    pub fn temperature(self, progress: f32) -> f32 {
+        match self {
+            CoolingSchedule::Hyperbolic { multiplier } => multiplier / progress.max(1e-7),
+            CoolingSchedule::Exponential { start, end } => start * (end / start).powf(progress),
+            CoolingSchedule::Polynomial { start, end, power } => {
+                end + (start - end) * (1.0 - progress).powf(power)
+            }
+        }
+    }
+}
+
+// "hyperbolic:1.5", "exponential:100,0.1", "linear:100,0.1" or
+// "polynomial:0.0235,0.001,2"
+impl FromStr for CoolingSchedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, raw_params) = s.split_once(':').unwrap_or((s, ""));
+        let params = raw_params
+            .split(',')
+            .filter(|param| !param.is_empty())
+            .map(|param| {
+                param
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|param| *param > 0.0)
+                    .ok_or_else(|| format!("bad cooling schedule parameter {param:?}"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(match (name, params.as_slice()) {
+            ("hyperbolic", []) => CoolingSchedule::Hyperbolic { multiplier: 1.5 },
+            ("hyperbolic", &[multiplier]) => CoolingSchedule::Hyperbolic { multiplier },
+            ("exponential", &[start, end]) => CoolingSchedule::Exponential { start, end },
+            ("linear", &[start, end]) => CoolingSchedule::Polynomial {
+                start,
+                end,
+                power: 1.0,
+            },
+            ("polynomial", &[start, end, power]) => {
+                CoolingSchedule::Polynomial { start, end, power }
+            }
+            _ => return Err(format!("bad cooling schedule {s:?}")),
+        })
+    }
+}
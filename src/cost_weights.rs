@@ -0,0 +1,117 @@
+use std::{fs, io::ErrorKind};
+
+use serde::Deserialize;
+
+use crate::{
+    solver::{Cost, HourCount},
+    types::Applicant,
+};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CostWeights {
+    // per assignment, by availability
+    pub dislike: Cost,
+    pub possible: Cost,
+    pub preferred: Cost,
+
+    // per extra day worked in a week, for tutors who'd like their sessions
+    // clustered, or per extra session on the same day, for those who'd like
+    // them spread out
+    pub day_preference: Cost,
+
+    // per tutor short of a session's minimum allocation
+    pub min_allocation_shortfall: Cost,
+
+    // weight * shortfall^exponent, for each tutor and week under their min hours
+    pub min_hours_shortfall: Cost,
+    pub min_hours_shortfall_exponent: f32,
+
+    // weight * shortfall^exponent, for each week under its desired hours
+    pub under_target: Cost,
+    pub under_target_exponent: u32,
+
+    // per hour over a week's desired hours
+    pub over_target: Cost,
+
+    // weight * (largest - smallest), when the largest non-priority session in
+    // a week has more than `size_imbalance_tolerance` tutors than the smallest
+    pub size_imbalance: Cost,
+    pub size_imbalance_tolerance: usize,
+
+    // per hour short of `term_minimum_hours`, for each tutor across the term
+    pub term_minimum_hours: HourCount,
+    pub term_minimum_shortfall: Cost,
+    // per hour short of a tutor's own min hours per term, which replaces
+    // `term_minimum_hours` for them
+    pub min_hours_per_term_shortfall: Cost,
+
+    // per assignment added or removed relative to a previous solution, when
+    // warm starting from one
+    pub change_penalty: Cost,
+
+    // per tutor who isn't in both of two consecutive weeks of a recurring
+    // session
+    pub continuity: Cost,
+
+    // weight * the squared difference between each tutor's hours across the
+    // term and their share of their course's hours, proportional to the hours
+    // they requested
+    pub fairness: Cost,
+}
+
+impl Default for CostWeights {
+    fn default() -> Self {
+        CostWeights {
+            dislike: 100,
+            possible: 5,
+            preferred: 0,
+            day_preference: 10,
+            min_allocation_shortfall: 50,
+            min_hours_shortfall: 20,
+            min_hours_shortfall_exponent: 1.5,
+            under_target: 20,
+            under_target_exponent: 2,
+            over_target: 200,
+            size_imbalance: 50,
+            size_imbalance_tolerance: 2,
+            term_minimum_hours: 6,
+            term_minimum_shortfall: 10,
+            min_hours_per_term_shortfall: 100,
+            change_penalty: 20,
+            continuity: 0,
+            fairness: 0,
+        }
+    }
+}
+
+impl CostWeights {
+    pub fn min_hours_shortfall_cost(&self, shortfall: HourCount) -> Cost {
+        self.min_hours_shortfall
+            * ((shortfall as f32).powf(self.min_hours_shortfall_exponent) as Cost)
+    }
+
+    // The hours a tutor should get across the term, and the cost per hour short
+    pub fn term_minimum(&self, applicant: &Applicant) -> (HourCount, Cost) {
+        match applicant.min_hours_per_term {
+            Some(min_hours) => (min_hours, self.min_hours_per_term_shortfall),
+            None => (self.term_minimum_hours, self.term_minimum_shortfall),
+        }
+    }
+
+    pub fn under_target_cost(&self, shortfall: HourCount) -> Cost {
+        self.under_target * (shortfall as Cost).pow(self.under_target_exponent)
+    }
+}
+
+pub fn read_cost_weights(path: &str) -> CostWeights {
+    match fs::read_to_string(path) {
+        Ok(contents) => toml::from_str(&contents)
+            .unwrap_or_else(|err| panic!("bad cost weights in {path:?}: {err}")),
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            println!("no {path}, using default cost weights");
+            CostWeights::default()
+        }
+        Err(err) => panic!("couldn't read {path:?}: {err}"),
+    }
+}
@@ -0,0 +1,209 @@
+use itertools::Itertools;
+
+use crate::{
+    solver::{HourCount, SolveInputs},
+    types::{Applicant, Availability, Session, WeekNum},
+};
+
+pub struct Finding {
+    // whether no allocation could satisfy it, rather than it just being tight
+    pub impossible: bool,
+    pub message: String,
+}
+
+// Warn when tutors can offer less than this much more than is wanted
+const TIGHT_SUPPLY_RATIO: f32 = 1.1;
+
+// The most hours `applicant` could do out of `session_indexes` (all in one
+// week), going by their availability and daily and weekly limits
+fn possible_hours(applicant: &Applicant, sessions: &[Session], session_indexes: &[usize]) -> u32 {
+    let by_day = session_indexes
+        .iter()
+        .map(|&session_index| &sessions[session_index])
+        .filter(|session| applicant.availabilities[session.id] != Availability::Impossible)
+        .map(|session| (session.day, session.length_hours as u32))
+        .into_group_map();
+
+    let hours = by_day
+        .into_values()
+        .map(|lengths| {
+            let day_hours = lengths.into_iter().sum::<u32>();
+            applicant
+                .max_hours_per_day
+                .map_or(day_hours, |max_hours| day_hours.min(max_hours as u32))
+        })
+        .sum::<u32>();
+
+    hours.min(applicant.max_hours_per_week as u32)
+}
+
+fn week_session_indexes(sessions: &[Session], week: WeekNum) -> Vec<usize> {
+    (0..sessions.len())
+        .filter(|&session_index| sessions[session_index].week == week)
+        .collect()
+}
+
+pub fn diagnose(inputs: &SolveInputs) -> Vec<Finding> {
+    let SolveInputs {
+        applicants,
+        sessions,
+        desired_hours,
+        frozen_weeks,
+        ..
+    } = *inputs;
+    let mut findings = vec![];
+
+    // supply vs demand for each course and week (frozen weeks being over
+    // and done with)
+    for &(course, week, desired_total_hours) in desired_hours {
+        if frozen_weeks.contains(&week) {
+            continue;
+        }
+
+        let session_indexes = (0..sessions.len())
+            .filter(|&session_index| {
+                sessions[session_index].course == course && sessions[session_index].week == week
+            })
+            .collect::<Vec<_>>();
+
+        let tutor_hours = applicants
+            .iter()
+            .map(|applicant| possible_hours(applicant, sessions, &session_indexes))
+            .sum::<u32>();
+        let room_hours = session_indexes
+            .iter()
+            .map(|&session_index| {
+                let session = &sessions[session_index];
+                (session.max_tutors() as u32) * (session.length_hours as u32)
+            })
+            .sum::<u32>();
+        let supply = tutor_hours.min(room_hours);
+        let desired = desired_total_hours as u32;
+
+        if supply < desired {
+            findings.push(Finding {
+                impossible: true,
+                message: format!(
+                    "{course} week {}: {desired} hours wanted, but at most {supply} can be staffed \
+                     ({tutor_hours} offered by tutors, {room_hours} fit in the sessions)",
+                    week.0
+                ),
+            });
+        } else if (supply as f32) < (desired as f32) * TIGHT_SUPPLY_RATIO {
+            findings.push(Finding {
+                impossible: false,
+                message: format!(
+                    "{course} week {}: {desired} hours wanted, and only {supply} can be staffed",
+                    week.0
+                ),
+            });
+        }
+    }
+
+    // sessions which can't reach their minimum allocation
+    for session in sessions {
+        let Some(min_allocation) = session.min_allocation else {
+            continue;
+        };
+        if frozen_weeks.contains(&session.week) {
+            continue;
+        }
+        let num_candidates = applicants
+            .iter()
+            .filter(|applicant| applicant.availabilities[session.id] != Availability::Impossible)
+            .count();
+        let most_tutors = num_candidates.min(session.max_tutors());
+
+        if most_tutors < min_allocation as usize {
+            findings.push(Finding {
+                impossible: true,
+                message: format!(
+                    "{} week {} {} {}:00 ({}) needs {min_allocation} tutors, but at most \
+                     {most_tutors} can be there",
+                    session.course,
+                    session.week.0,
+                    session.day.long_name(),
+                    session.time_24hr,
+                    session.location
+                ),
+            });
+        }
+    }
+
+    // tutors whose weekly or term minimums can't be met
+    let weeks = sessions
+        .iter()
+        .map(|session| session.week)
+        .unique()
+        .sorted();
+    let week_session_indexes = weeks
+        .map(|week| (week, week_session_indexes(sessions, week)))
+        .collect::<Vec<_>>();
+
+    for applicant in applicants {
+        let weekly_possible_hours = week_session_indexes
+            .iter()
+            .map(|(week, session_indexes)| {
+                (*week, possible_hours(applicant, sessions, session_indexes))
+            })
+            .collect::<Vec<_>>();
+
+        // weeks they can't do at all are taken as weeks off rather than a problem
+        if let Some(min_hours) = applicant.min_hours_per_week {
+            let short_weeks = weekly_possible_hours
+                .iter()
+                .filter(|(week, hours)| {
+                    !frozen_weeks.contains(week) && *hours > 0 && *hours < min_hours as u32
+                })
+                .map(|(week, hours)| format!("{hours} in week {}", week.0))
+                .collect::<Vec<_>>();
+            if !short_weeks.is_empty() {
+                findings.push(Finding {
+                    impossible: true,
+                    message: format!(
+                        "{} ({}) wants at least {min_hours} hours a week, but can only do {}",
+                        applicant.zid,
+                        applicant.name,
+                        short_weeks.join(", ")
+                    ),
+                });
+            }
+        }
+
+        if let Some(min_hours) = applicant.min_hours_per_term {
+            let term_hours = weekly_possible_hours
+                .iter()
+                .map(|(_, hours)| hours)
+                .sum::<u32>()
+                .min(applicant.max_hours_per_term.unwrap_or(HourCount::MAX) as u32);
+            if term_hours < min_hours as u32 {
+                findings.push(Finding {
+                    impossible: true,
+                    message: format!(
+                        "{} ({}) needs at least {min_hours} hours this term, but can only do \
+                         {term_hours}",
+                        applicant.zid, applicant.name
+                    ),
+                });
+            }
+        }
+    }
+
+    findings
+}
+
+pub fn print_diagnostics(findings: &[Finding]) {
+    if findings.is_empty() {
+        println!("diagnostics: no problems found");
+        return;
+    }
+
+    for finding in findings {
+        let level = if finding.impossible {
+            "impossible"
+        } else {
+            "warning"
+        };
+        println!("{level}: {}", finding.message);
+    }
+}
@@ -0,0 +1,297 @@
+use itertools::Itertools;
+use microlp::{ComparisonOp, LinearExpr, OptimizationDirection, Problem, Variable};
+
+use crate::{
+    pins::PinnedAssignment,
+    solver::{Cost, HourCount, SolveInputs},
+    types::{Applicant, Availability},
+};
+
+pub struct ExactSolution {
+    pub objective: f64,
+    // applicant indexes assigned to each session
+    pub assigned: Vec<Vec<usize>>,
+}
+
+// Lines (slope, intercept) making up the lower convex hull of `cost(k)` for
+// k = 0..=max. At the integer points this never exceeds `cost`, even when the
+// rounding in the cost terms makes it slightly non-convex, so using these as
+// lower bounds on a penalty variable keeps the model a relaxation.
+fn convex_underestimate(max: HourCount, cost: impl Fn(HourCount) -> Cost) -> Vec<(f64, f64)> {
+    let mut hull: Vec<(f64, f64)> = vec![];
+
+    for k in 0..=max {
+        let point = (k as f64, cost(k) as f64);
+        while hull.len() >= 2 {
+            let (x1, y1) = hull[hull.len() - 2];
+            let (x2, y2) = hull[hull.len() - 1];
+            // drop the middle point if it's on or above the line to the new point
+            if (y2 - y1) * (point.0 - x1) >= (point.1 - y1) * (x2 - x1) {
+                hull.pop();
+            } else {
+                break;
+            }
+        }
+        hull.push(point);
+    }
+
+    hull.windows(2)
+        .map(|pair| {
+            let ((x1, y1), (x2, y2)) = (pair[0], pair[1]);
+            let slope = (y2 - y1) / (x2 - x1);
+            (slope, y1 - slope * x1)
+        })
+        .collect()
+}
+
+// Adds a variable which is at least `cost(shortfall)`, where `shortfall` is at
+// least `target - hours`.
+fn add_convex_penalty(
+    problem: &mut Problem,
+    hours: &LinearExpr,
+    target: HourCount,
+    cost: impl Fn(HourCount) -> Cost,
+) {
+    let shortfall = problem.add_var(0.0, (0.0, target as f64));
+    let mut shortfall_constraint = hours.clone();
+    shortfall_constraint.add(shortfall, 1.0);
+    problem.add_constraint(shortfall_constraint, ComparisonOp::Ge, target as f64);
+
+    let penalty = problem.add_var(1.0, (0.0, f64::INFINITY));
+    for (slope, intercept) in convex_underestimate(target, cost) {
+        problem.add_constraint(
+            [(penalty, 1.0), (shortfall, -slope)],
+            ComparisonOp::Ge,
+            intercept,
+        );
+    }
+}
+
+// Builds and solves a (mixed) integer program which is a relaxation of the
+// solver's cost model: the convex penalties are replaced by piecewise-linear
+// underestimates, and the session size imbalance, day preference, continuity and
+// fairness penalties are dropped. The
+// objective is therefore a lower bound on the cost of any allocation. With
+// `integral` unset the assignment variables are continuous, which gives a
+// weaker bound much more quickly.
+pub fn solve_relaxation(inputs: &SolveInputs, integral: bool) -> ExactSolution {
+    let SolveInputs {
+        applicants,
+        sessions,
+        desired_hours,
+        weights,
+        previous,
+        pinned,
+        conflicts,
+        frozen_weeks,
+    } = *inputs;
+    let mut problem = Problem::new(OptimizationDirection::Minimize);
+
+    // keeping a previous assignment saves the change penalty which would
+    // otherwise be paid for dropping it, so its cost is offset by a constant
+    let mut constant_cost = 0.0;
+    let previously_assigned = |session_index: usize, applicant: &Applicant| {
+        previous.unwrap_or_default().iter().any(|assignment| {
+            assignment.session.id == session_index
+                && assignment
+                    .applicants
+                    .iter()
+                    .any(|previous_applicant| previous_applicant.zid == applicant.zid)
+        })
+    };
+
+    // assign[session] = [(applicant index, variable)]
+    let assign: Vec<Vec<(usize, Variable)>> = (0..sessions.len())
+        .map(|session_index| {
+            applicants
+                .iter()
+                .enumerate()
+                .filter_map(|(applicant_index, applicant)| {
+                    let preference_cost = match applicant.availabilities[session_index] {
+                        Availability::Impossible => return None,
+                        Availability::Dislike => weights.dislike,
+                        Availability::Possible => weights.possible,
+                        Availability::Preferred => weights.preferred,
+                    } as f64;
+                    let change_penalty = weights.change_penalty as f64;
+                    let cost = if previously_assigned(session_index, applicant) {
+                        constant_cost += change_penalty;
+                        preference_cost - change_penalty
+                    } else if previous.is_some() {
+                        preference_cost + change_penalty
+                    } else {
+                        preference_cost
+                    };
+                    let pin = PinnedAssignment {
+                        session: session_index,
+                        applicant: applicant_index,
+                    };
+                    let variable = if pinned.contains(&pin) {
+                        problem.add_var(cost, (1.0, 1.0))
+                    } else if frozen_weeks.contains(&sessions[session_index].week) {
+                        let kept = previously_assigned(session_index, applicant) as u8 as f64;
+                        problem.add_var(cost, (kept, kept))
+                    } else if integral {
+                        problem.add_binary_var(cost)
+                    } else {
+                        problem.add_var(cost, (0.0, 1.0))
+                    };
+                    Some((applicant_index, variable))
+                })
+                .collect()
+        })
+        .collect();
+
+    let hours_expr = |session_indexes: &mut dyn Iterator<Item = usize>,
+                      applicant: Option<usize>| {
+        let mut expr = LinearExpr::empty();
+        for session_index in session_indexes {
+            for &(applicant_index, variable) in &assign[session_index] {
+                if applicant.is_none_or(|applicant| applicant == applicant_index) {
+                    expr.add(variable, sessions[session_index].length_hours as f64);
+                }
+            }
+        }
+        expr
+    };
+
+    for (session_index, session) in sessions.iter().enumerate() {
+        let tutors = assign[session_index]
+            .iter()
+            .map(|&(_, variable)| (variable, 1.0))
+            .collect::<LinearExpr>();
+        problem.add_constraint(
+            tutors.clone(),
+            ComparisonOp::Le,
+            session.max_tutors() as f64,
+        );
+
+        if let Some(min_allocation) = session.min_allocation {
+            let mut shortfall_constraint = tutors;
+            let shortfall = problem.add_var(
+                weights.min_allocation_shortfall as f64,
+                (0.0, min_allocation as f64),
+            );
+            shortfall_constraint.add(shortfall, 1.0);
+            problem.add_constraint(
+                shortfall_constraint,
+                ComparisonOp::Ge,
+                min_allocation as f64,
+            );
+        }
+    }
+
+    let calendar_weeks = sessions.iter().map(|session| session.week).unique();
+    for week_num in calendar_weeks {
+        let week_sessions = (0..sessions.len())
+            .filter(|&session_index| sessions[session_index].week == week_num)
+            .collect::<Vec<_>>();
+
+        for (applicant_index, applicant) in applicants.iter().enumerate() {
+            let weekly_hours =
+                hours_expr(&mut week_sessions.iter().copied(), Some(applicant_index));
+            problem.add_constraint(
+                weekly_hours.clone(),
+                ComparisonOp::Le,
+                applicant.max_hours_per_week as f64,
+            );
+
+            if let Some(min_hours) = applicant.min_hours_per_week {
+                add_convex_penalty(&mut problem, &weekly_hours, min_hours, |shortfall| {
+                    weights.min_hours_shortfall_cost(shortfall)
+                });
+            }
+        }
+    }
+
+    let calendar_days = sessions
+        .iter()
+        .map(|session| (session.week, session.day))
+        .unique();
+    for (week_num, day) in calendar_days {
+        let day_sessions = (0..sessions.len()).filter(|&session_index| {
+            sessions[session_index].week == week_num && sessions[session_index].day == day
+        });
+        for (applicant_index, applicant) in applicants.iter().enumerate() {
+            if let Some(max_hours) = applicant.max_hours_per_day {
+                let daily_hours = hours_expr(&mut day_sessions.clone(), Some(applicant_index));
+                problem.add_constraint(daily_hours, ComparisonOp::Le, max_hours as f64);
+            }
+        }
+    }
+
+    for &(course, week_num, desired_total_hours) in desired_hours {
+        let week_sessions = (0..sessions.len()).filter(|&session_index| {
+            sessions[session_index].course == course && sessions[session_index].week == week_num
+        });
+        let effective_hours = hours_expr(&mut week_sessions.into_iter(), None);
+
+        let mut over_target_constraint = effective_hours.clone();
+        let over_target = problem.add_var(weights.over_target as f64, (0.0, f64::INFINITY));
+        over_target_constraint.add(over_target, -1.0);
+        problem.add_constraint(
+            over_target_constraint,
+            ComparisonOp::Le,
+            desired_total_hours as f64,
+        );
+
+        add_convex_penalty(
+            &mut problem,
+            &effective_hours,
+            desired_total_hours,
+            |shortfall| weights.under_target_cost(shortfall),
+        );
+    }
+
+    // a tutor can't be in two clashing sessions
+    for (session_index, session_conflicts) in conflicts.iter().enumerate() {
+        for &other_index in session_conflicts {
+            if other_index < session_index {
+                continue;
+            }
+            for &(applicant_index, variable) in &assign[session_index] {
+                if let Some(&(_, other_variable)) = assign[other_index]
+                    .iter()
+                    .find(|&&(other_applicant, _)| other_applicant == applicant_index)
+                {
+                    problem.add_constraint(
+                        [(variable, 1.0), (other_variable, 1.0)],
+                        ComparisonOp::Le,
+                        1.0,
+                    );
+                }
+            }
+        }
+    }
+
+    for (applicant_index, applicant) in applicants.iter().enumerate() {
+        let term_hours = hours_expr(&mut (0..sessions.len()), Some(applicant_index));
+        if let Some(max_hours) = applicant.max_hours_per_term {
+            problem.add_constraint(term_hours.clone(), ComparisonOp::Le, max_hours as f64);
+        }
+
+        let (term_minimum, shortfall_cost) = weights.term_minimum(applicant);
+        let mut shortfall_constraint = term_hours;
+        let shortfall = problem.add_var(shortfall_cost as f64, (0.0, term_minimum as f64));
+        shortfall_constraint.add(shortfall, 1.0);
+        problem.add_constraint(shortfall_constraint, ComparisonOp::Ge, term_minimum as f64);
+    }
+
+    let solution = problem
+        .solve()
+        .unwrap_or_else(|err| panic!("couldn't solve the integer program: {err}"));
+
+    ExactSolution {
+        objective: solution.objective() + constant_cost,
+        assigned: assign
+            .iter()
+            .map(|session_assign| {
+                session_assign
+                    .iter()
+                    .filter(|&&(_, variable)| solution[variable] > 0.5)
+                    .map(|&(applicant_index, _)| applicant_index)
+                    .collect()
+            })
+            .collect(),
+    }
+}
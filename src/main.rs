@@ -1,98 +1,781 @@
-use std::fs;
+use std::{
+    fs, process,
+    time::{Duration, Instant},
+};
 
 use clap::Parser;
-use read_responses::extract_applicants_from_tsv;
-use read_sessions::{
-    apply_priorities, expand_sequence_specification, extract_desired_hours,
-    read_sessions_from_string,
-};
+use cooling::CoolingSchedule;
+use diagnostics::{diagnose, print_diagnostics};
+use itertools::Itertools;
+use problem::Problem;
+use read_sessions::expand_sequence_specification;
 
+use solution_diff::{
+    diff_solutions, file_assignments, print_solution_diff, solved_assignments,
+    tabulate_solution_diff,
+};
 use solution_output::{
-    convert_to_json_output, output_to_atci_toml, tabulate_hours_by_tutor, tabulate_solution_info,
+    convert_alternative_to_json_output, convert_to_json_output, count_changed_assignments,
+    output_to_atci_toml, read_json_output, tabulate_cost_breakdown, tabulate_hours_by_tutor,
+    tabulate_solution_info, SolvedSession,
 };
-use solver::solve_many_times;
-use tsv::Tsv;
-use types::Course;
+use solver::{
+    cost_breakdown, evaluate_solution, explain, keepable_assignments, lower_bound, solve_exactly,
+    solve_many_times, solve_with_tempering, CostItem, Obstacle, SolveInputs, SolveOptions,
+    TemperingOptions, Violation,
+};
+use types::{Course, Day, Session, WeekNum};
 
+mod cooling;
+mod cost_weights;
+mod diagnostics;
+mod exact;
+mod pins;
+mod problem;
 mod read_responses;
 mod read_sessions;
+mod solution_diff;
 mod solution_output;
 mod solver;
 mod tsv;
 mod types;
 
 #[derive(clap::Parser, Debug)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    solve: Option<Args>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Explain why a tutor is or isn't in a session of a solution
+    Explain(ExplainArgs),
+    /// Show which tutors gained or lost sessions between two solutions
+    Diff(DiffArgs),
+    /// Find the cost of a solution (e.g. after editing it by hand), and
+    /// anything in it which breaks the hard constraints
+    Evaluate(EvaluateArgs),
+    /// Fix a solution which no longer fits the inputs, keeping every
+    /// assignment that still can be and changing as few others as possible
+    Repair(RepairArgs),
+}
+
+#[derive(clap::Args, Debug)]
 struct Args {
-    course: Course,
+    /// A course, or several separated by commas to solve them together so
+    /// that tutors shared between them aren't double-booked
+    #[arg(value_delimiter = ',', num_args = 1, required = true)]
+    courses: Vec<Course>,
     seed: String,
     #[arg(long)]
     no_write: bool,
+    /// Shorthand for --steps 30000
     #[arg(long)]
     quick: bool,
+    /// Number of annealing steps for each seed (8000000 by default, unless
+    /// there's a time limit)
+    #[arg(long)]
+    steps: Option<u64>,
+    /// Stop all seeds after this long, e.g. 90s, 2m or 1h
+    #[arg(long, value_parser = parse_duration)]
+    time_limit: Option<Duration>,
+    /// Stop a seed once its best cost hasn't improved in this many steps
+    #[arg(long)]
+    stagnation: Option<u64>,
+    /// Annealing temperature schedule: hyperbolic[:multiplier],
+    /// exponential:start,end, linear:start,end or polynomial:start,end,power
+    #[arg(long, default_value = "hyperbolic:1.5", conflicts_with = "tempering")]
+    cooling: CoolingSchedule,
+    /// Number of times to restart the cooling schedule partway through
+    #[arg(long, default_value_t = 0, conflicts_with = "tempering")]
+    reheats: u32,
+    /// Go back to the best allocation so far on each reheat
+    #[arg(long, conflicts_with = "tempering")]
+    restart_from_best: bool,
+    /// Solve with the integer programming backend instead of annealing. It
+    /// can't be stopped early, so it can run for hours on anything but a
+    /// small course
+    #[arg(long, conflicts_with_all = ["quick", "steps", "time_limit", "stagnation"])]
+    exact: bool,
+    /// Report a lower bound on the cost (from the linear relaxation of the
+    /// integer program) alongside the annealed solution
+    #[arg(long)]
+    bound: bool,
+    /// Run the seeds as replicas at different temperatures which swap states,
+    /// rather than as independent solves
+    #[arg(long)]
+    tempering: bool,
+    #[arg(long, default_value_t = 0.5)]
+    tempering_min_temperature: f32,
+    #[arg(long, default_value_t = 20.0)]
+    tempering_max_temperature: f32,
+    /// Steps between attempts to exchange states when tempering
+    #[arg(long, default_value_t = 10000)]
+    exchange_interval: u64,
+    /// A previously output help_sessions JSON file to start from, keeping as
+    /// much of it as possible (repeat for each course when solving several)
+    #[arg(long)]
+    warm_start: Vec<String>,
+    /// Weeks to keep exactly as they are in the --warm-start solution, e.g.
+    /// 3-6, while re-solving the rest
+    #[arg(long, requires = "warm_start")]
+    freeze_weeks: Option<String>,
+    /// Minimum hours between an f2f and an online session on the same day for
+    /// one tutor, to allow for the commute
+    #[arg(long, default_value_t = 0)]
+    commute_gap: u8,
+    #[arg(long, default_value = "weights.toml")]
+    weights: String,
+    #[arg(long, default_value_t = 0.15)]
+    move_probability: f32,
+    #[arg(long, default_value_t = 0.15)]
+    swap_probability: f32,
+    #[arg(long, default_value_t = 0.15)]
+    replace_probability: f32,
+    /// Solve even if the diagnostics find that some targets can't be met
+    #[arg(long)]
+    allow_impossible: bool,
+    /// Also write up to this many other good solutions from different seeds,
+    /// as help_sessions.<course>.alt<N>.json
+    #[arg(long, default_value_t = 0)]
+    alternatives: usize,
+    /// How many tutor-session assignments each alternative must differ by,
+    /// from the best solution and from each other
+    #[arg(long, default_value_t = 10)]
+    alternative_distance: usize,
+    /// Check the running cost against a full evaluation after every step (slow)
+    #[arg(long)]
+    check_costs: bool,
+}
+
+// The same as those of `Args` for reading the problem, for the subcommands
+// which look at a solution to it
+#[derive(clap::Args, Debug)]
+struct ProblemArgs {
+    #[arg(value_delimiter = ',', num_args = 1, required = true)]
+    courses: Vec<Course>,
+    #[arg(long, default_value_t = 0)]
+    commute_gap: u8,
+    #[arg(long, default_value = "weights.toml")]
+    weights: String,
+    /// Solution files to look at, JSON or TOML (help_sessions.<course>.json
+    /// for each course by default)
+    #[arg(long)]
+    solution: Vec<String>,
+}
+
+impl ProblemArgs {
+    fn read(&self) -> (Problem, Vec<SolvedSession>) {
+        let problem = read_problem(&self.courses, self.commute_gap, &self.weights);
+        let solution = problem.read_solutions(&self.solution_paths(&problem), |violation| {
+            println!(
+                "warning: {} (ignoring it)",
+                describe_violation(&problem, &violation)
+            )
+        });
+        (problem, solution)
+    }
+
+    fn solution_paths(&self, problem: &Problem) -> Vec<String> {
+        if self.solution.is_empty() {
+            problem.default_solution_paths()
+        } else {
+            self.solution.clone()
+        }
+    }
+}
+
+#[derive(clap::Args, Debug)]
+struct ExplainArgs {
+    #[command(flatten)]
+    problem: ProblemArgs,
+    zid: String,
+    #[arg(long)]
+    week: u8,
+    /// e.g. mon or Monday
+    #[arg(long)]
+    day: Day,
+    /// Starting hour of the session, in 24 hour time
+    #[arg(long)]
+    start: u8,
+    /// The session's course, if solving several
+    #[arg(long)]
+    course: Option<Course>,
+}
+
+#[derive(clap::Args, Debug)]
+struct EvaluateArgs {
+    #[command(flatten)]
+    problem: ProblemArgs,
+    #[arg(long)]
+    no_write: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct DiffArgs {
+    /// A help_sessions JSON file from before
+    before: String,
+    /// A help_sessions JSON file from after
+    after: String,
+    /// Where to write the changes for each tutor
+    #[arg(long, default_value = "diff.tsv")]
+    output: String,
+    #[arg(long)]
+    no_write: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct RepairArgs {
+    #[command(flatten)]
+    problem: ProblemArgs,
+    #[arg(long, default_value = "1")]
+    seed: String,
+    /// Number of annealing steps for each seed
+    #[arg(long, default_value_t = 1000000)]
+    steps: u64,
+    /// Where to write the changes for each tutor
+    #[arg(long, default_value = "repair.tsv")]
+    output: String,
+    #[arg(long)]
+    no_write: bool,
+}
+
+fn read_problem(courses: &[Course], commute_gap: u8, weights: &str) -> Problem {
+    let courses = courses.iter().copied().unique().collect();
+    Problem::read(courses, commute_gap, weights)
+}
+
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let (number, seconds_per_unit) = if let Some(number) = s.strip_suffix('h') {
+        (number, 3600.0)
+    } else if let Some(number) = s.strip_suffix('m') {
+        (number, 60.0)
+    } else {
+        (s.strip_suffix('s').unwrap_or(s), 1.0)
+    };
+
+    number
+        .parse::<f64>()
+        .ok()
+        .filter(|number| *number >= 0.0)
+        .map(|number| Duration::from_secs_f64(number * seconds_per_unit))
+        .ok_or_else(|| format!("bad duration {s:?}"))
+}
+
+fn print_term_totals(breakdown: &[CostItem]) {
+    for (term, items) in &breakdown
+        .iter()
+        .sorted_by_key(|item| item.term)
+        .chunk_by(|item| item.term)
+    {
+        println!(
+            "{}: {}",
+            term.name(),
+            items.map(|item| item.cost).sum::<u64>()
+        );
+    }
+}
+
+fn describe_session(session: &Session) -> String {
+    format!(
+        "{} week {} {} {}:00 ({})",
+        session.course,
+        session.week.0,
+        session.day.long_name(),
+        session.time_24hr,
+        session.location
+    )
+}
+
+fn describe_violation(problem: &Problem, violation: &Violation) -> String {
+    let applicant = |applicant_index: usize| {
+        let applicant = &problem.applicants[applicant_index];
+        format!("{} ({})", applicant.zid, applicant.name)
+    };
+    match *violation {
+        Violation::UnknownSession { ref description } => {
+            format!("{description} doesn't exist")
+        }
+        Violation::UnknownTutor { session, ref zid } => format!(
+            "{zid} in {} isn't an applicant",
+            describe_session(&problem.sessions[session])
+        ),
+        Violation::Impossible {
+            session,
+            applicant: applicant_index,
+        } => format!(
+            "{} can't make {}",
+            applicant(applicant_index),
+            describe_session(&problem.sessions[session])
+        ),
+        Violation::Duplicate {
+            session,
+            applicant: applicant_index,
+        } => format!(
+            "{} is listed more than once in {}",
+            applicant(applicant_index),
+            describe_session(&problem.sessions[session])
+        ),
+        Violation::Clash {
+            session,
+            other,
+            applicant: applicant_index,
+        } => format!(
+            "{} is in both {} and {}, which clash",
+            applicant(applicant_index),
+            describe_session(&problem.sessions[session]),
+            describe_session(&problem.sessions[other])
+        ),
+        Violation::Overfull {
+            session,
+            num_tutors,
+            max_tutors,
+        } => format!(
+            "{} has {num_tutors} tutors, more than its maximum of {max_tutors}",
+            describe_session(&problem.sessions[session])
+        ),
+        Violation::WeeklyCap {
+            applicant: applicant_index,
+            week,
+            hours,
+            max_hours,
+        } => format!(
+            "{} has {hours} hours in week {}, more than their maximum of {max_hours}",
+            applicant(applicant_index),
+            week.0
+        ),
+        Violation::DailyCap {
+            applicant: applicant_index,
+            week,
+            day,
+            hours,
+            max_hours,
+        } => format!(
+            "{} has {hours} hours on {} of week {}, more than their maximum of {max_hours}",
+            applicant(applicant_index),
+            day.long_name(),
+            week.0
+        ),
+        Violation::TermCap {
+            applicant: applicant_index,
+            hours,
+            max_hours,
+        } => format!(
+            "{} has {hours} hours over the term, more than their maximum of {max_hours}",
+            applicant(applicant_index)
+        ),
+    }
+}
+
+fn print_gap(cost: u64, lower_bound: f64) {
+    let gap = if cost == 0 {
+        0.0
+    } else {
+        100.0 * (cost as f64 - lower_bound).max(0.0) / (cost as f64)
+    };
+    println!("cost = {cost}, lower bound = {lower_bound:.1}, within {gap:.1}% of optimal");
 }
 
 fn main() {
-    let args = Args::parse();
-    let course = args.course;
+    let cli = Cli::parse();
 
     println!("{}", "=".repeat(80));
-    println!("{:?}", args);
+    println!("{:?}", cli);
     println!("{}", "-".repeat(80));
 
-    let sessions = {
-        let mut sessions = read_sessions_from_string(&fs::read_to_string("sessions.txt").unwrap());
-        let priorities = Tsv::from_string(&fs::read_to_string("priorities.tsv").unwrap());
-        apply_priorities(args.course, &priorities, &mut sessions);
-        sessions
-    };
+    match cli.command {
+        Some(Command::Explain(args)) => run_explain(args),
+        Some(Command::Diff(args)) => run_diff(args),
+        Some(Command::Evaluate(args)) => run_evaluate(args),
+        Some(Command::Repair(args)) => run_repair(args),
+        None => run_solve(cli.solve.expect("no arguments")),
+    }
+}
+
+fn run_solve(args: Args) {
+    assert!(
+        args.move_probability + args.swap_probability + args.replace_probability <= 1.0,
+        "move, swap and replace probabilities add up to more than 1"
+    );
 
-    println!("{} sessions to schedule", sessions.len());
+    let mut problem = read_problem(&args.courses, args.commute_gap, &args.weights);
 
-    let responses = Tsv::from_string(&fs::read_to_string("responses.tsv").unwrap());
-    println!("{} form responses", responses.num_rows());
+    let frozen_weeks = match &args.freeze_weeks {
+        Some(spec) => expand_sequence_specification(spec)
+            .into_iter()
+            .map(|week| WeekNum(week as u8))
+            .collect(),
+        None => vec![],
+    };
+    // before reading the previous solution, which would drop anyone who has left
+    if !frozen_weeks.is_empty() {
+        problem.allow_frozen_assignments(&frozen_weeks, &args.warm_start);
+    }
+    let previous = (!args.warm_start.is_empty()).then(|| {
+        problem.read_solutions(&args.warm_start, |violation| {
+            println!(
+                "warning: {} (ignoring it)",
+                describe_violation(&problem, &violation)
+            )
+        })
+    });
 
-    let desired_hours_tsv = Tsv::from_string(&fs::read_to_string("desired_hours.tsv").unwrap());
-    let desired_hours = extract_desired_hours(desired_hours_tsv, course);
+    let Problem {
+        courses,
+        sessions,
+        applicants,
+        ..
+    } = &problem;
+    let inputs = SolveInputs {
+        frozen_weeks: &frozen_weeks,
+        ..problem.inputs(previous.as_deref())
+    };
 
-    let applicants = extract_applicants_from_tsv(responses, &sessions);
+    let findings = diagnose(&inputs);
+    print_diagnostics(&findings);
+    if findings.iter().any(|finding| finding.impossible) && !args.allow_impossible {
+        println!("stopping because of the impossible targets above (see --allow-impossible)");
+        process::exit(1);
+    }
 
-    let (solution, best_seed) = solve_many_times(
-        expand_sequence_specification(&args.seed)
+    let (solution, best_seed, alternatives) = if args.exact {
+        let (solution, cost, bound) = solve_exactly(courses, &inputs);
+        print_gap(cost, bound);
+        (solution, 0, vec![])
+    } else {
+        let seeds = expand_sequence_specification(&args.seed)
             .into_iter()
             .map(|seed| seed as u64)
-            .collect(),
-        course,
-        &applicants,
-        &sessions,
-        &desired_hours,
-        args.quick,
+            .collect();
+        let options = SolveOptions {
+            steps: args.steps.or(match (args.time_limit, args.quick) {
+                (Some(_), _) => None,
+                (None, true) => Some(30000),
+                (None, false) => Some(8000000),
+            }),
+            deadline: args
+                .time_limit
+                .map(|time_limit| Instant::now() + time_limit),
+            stagnation_limit: args.stagnation,
+            cooling: args.cooling,
+            reheats: args.reheats,
+            restart_from_best: args.restart_from_best,
+            check_costs: args.check_costs,
+            move_probability: args.move_probability,
+            swap_probability: args.swap_probability,
+            replace_probability: args.replace_probability,
+            alternatives: args.alternatives,
+            alternative_distance: args.alternative_distance,
+        };
+
+        let (solution, best_seed, best_cost, alternatives) = if args.tempering {
+            solve_with_tempering(
+                seeds,
+                courses,
+                &inputs,
+                &options,
+                &TemperingOptions {
+                    min_temperature: args.tempering_min_temperature,
+                    max_temperature: args.tempering_max_temperature,
+                    exchange_interval: args.exchange_interval,
+                },
+            )
+        } else {
+            solve_many_times(seeds, courses, &inputs, &options)
+        };
+
+        if args.bound {
+            let bound = lower_bound(&inputs);
+            print_gap(best_cost, bound);
+        }
+
+        for (number, alternative) in alternatives.iter().enumerate() {
+            println!(
+                "alternative {}: cost = {} (+{}) with seed {}, {} assignments different",
+                number + 1,
+                alternative.cost,
+                alternative.cost - best_cost,
+                alternative.seed,
+                alternative.distance
+            );
+        }
+
+        (solution, best_seed, alternatives)
+    };
+
+    if let Some(previous) = &previous {
+        println!(
+            "{} assignments changed from the previous solution",
+            count_changed_assignments(previous, &solution)
+        );
+    }
+
+    let breakdown = cost_breakdown(&inputs, &solution);
+    print_term_totals(&breakdown);
+
+    for &course in courses {
+        let solution = solution
+            .iter()
+            .filter(|assignment| assignment.session.course == course)
+            .cloned()
+            .collect::<Vec<_>>();
+        let course_applicants = applicants
+            .iter()
+            .filter(|applicant| applicant.course == course)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let solution_info = tabulate_solution_info(solution.clone());
+
+        if !args.no_write {
+            fs::write(format!("solution.{}.tsv", course), solution_info).unwrap();
+
+            fs::write(
+                format!("hours.{}.tsv", course),
+                tabulate_hours_by_tutor(solution.clone(), &course_applicants),
+            )
+            .unwrap();
+
+            fs::write(
+                format!("help_sessions.{}.toml", course),
+                output_to_atci_toml(solution.clone(), best_seed),
+            )
+            .unwrap();
+
+            fs::write(
+                format!("help_sessions.{}.json", course),
+                convert_to_json_output(solution.clone(), best_seed, course),
+            )
+            .unwrap();
+
+            fs::write(
+                format!("cost_breakdown.{}.tsv", course),
+                tabulate_cost_breakdown(&breakdown, sessions, applicants, course),
+            )
+            .unwrap();
+        }
+    }
+
+    if !args.no_write {
+        for (number, alternative) in alternatives.into_iter().enumerate() {
+            for &course in courses {
+                let course_solution = |solution: &[SolvedSession]| {
+                    solution
+                        .iter()
+                        .filter(|assignment| assignment.session.course == course)
+                        .cloned()
+                        .collect::<Vec<_>>()
+                };
+                let best = course_solution(&solution);
+                let alternative_solution = course_solution(&alternative.solution);
+
+                fs::write(
+                    format!("help_sessions.{}.alt{}.json", course, number + 1),
+                    convert_alternative_to_json_output(
+                        alternative_solution.clone(),
+                        alternative.seed,
+                        course,
+                        alternative.cost,
+                        count_changed_assignments(&best, &alternative_solution),
+                    ),
+                )
+                .unwrap();
+            }
+        }
+    }
+}
+
+fn run_explain(args: ExplainArgs) {
+    let (problem, solution) = args.problem.read();
+
+    let session_index = problem.find_session(args.course, WeekNum(args.week), args.day, args.start);
+    let applicant_index = problem.find_applicant(&args.zid);
+    let session = &problem.sessions[session_index];
+    let applicant = &problem.applicants[applicant_index];
+
+    let explanation = explain(
+        &problem.inputs(None),
+        &solution,
+        session_index,
+        applicant_index,
+    );
+
+    // the solution may be out of date, which is often why someone is asking
+    for violation in &explanation.violations {
+        println!(
+            "warning: the solution no longer fits the inputs: {}",
+            describe_violation(&problem, violation)
+        );
+    }
+
+    println!(
+        "{} ({}) in {}:",
+        applicant.zid,
+        applicant.name,
+        describe_session(session)
+    );
+    println!(
+        "  {} assigned, marked as {:?}",
+        if explanation.assigned { "is" } else { "isn't" },
+        explanation.availability
+    );
+    println!(
+        "  {} week {} has {} of its {} desired hours",
+        session.course, session.week.0, explanation.week_hours, explanation.desired_hours
     );
 
-    let solution_info = tabulate_solution_info(solution.clone());
+    let action = if explanation.assigned {
+        "removing"
+    } else {
+        "adding"
+    };
+    if !explanation.obstacles.is_empty() {
+        println!("  {action} them isn't possible:");
+        for obstacle in &explanation.obstacles {
+            let reason = match *obstacle {
+                Obstacle::Impossible => "they can't make it".to_string(),
+                Obstacle::Pinned => "they're pinned there".to_string(),
+                Obstacle::Full { max_tutors } => {
+                    format!("the session already has its maximum of {max_tutors} tutors")
+                }
+                Obstacle::Clash { session } => format!(
+                    "they're in {}, which clashes",
+                    describe_session(&problem.sessions[session])
+                ),
+                Obstacle::WeeklyCap { hours, max_hours } => {
+                    format!("they already have {hours} of their {max_hours} hours that week")
+                }
+                Obstacle::DailyCap { hours, max_hours } => {
+                    format!("they already have {hours} of their {max_hours} hours that day")
+                }
+                Obstacle::TermCap { hours, max_hours } => {
+                    format!("they already have {hours} of their {max_hours} hours for the term")
+                }
+            };
+            println!("    {reason}");
+        }
+        return;
+    }
+
+    let total_change = explanation
+        .cost_changes
+        .iter()
+        .map(|(_, change)| change)
+        .sum::<i64>();
+    println!("  {action} them would change the cost by {total_change:+}:");
+    for (term, change) in &explanation.cost_changes {
+        println!("    {}: {change:+}", term.name());
+    }
+}
+
+fn run_diff(args: DiffArgs) {
+    let before = read_json_output(&fs::read_to_string(&args.before).unwrap());
+    let after = read_json_output(&fs::read_to_string(&args.after).unwrap());
+
+    let changes = diff_solutions(file_assignments(&before), file_assignments(&after));
+    print_solution_diff(&changes);
 
     if !args.no_write {
-        fs::write(
-            format!("solution.{}.tsv", course.to_string()),
-            solution_info,
-        )
-        .unwrap();
-
-        fs::write(
-            format!("hours.{}.tsv", course.to_string()),
-            tabulate_hours_by_tutor(solution.clone()),
-        )
-        .unwrap();
-
-        fs::write(
-            format!("help_sessions.{}.toml", course.to_string()),
-            output_to_atci_toml(solution.clone(), best_seed),
-        )
-        .unwrap();
-
-        fs::write(
-            format!("help_sessions.{}.json", course.to_string()),
-            convert_to_json_output(solution.clone(), best_seed, course),
-        )
-        .unwrap();
+        fs::write(&args.output, tabulate_solution_diff(&changes)).unwrap();
+    }
+}
+
+fn run_evaluate(args: EvaluateArgs) {
+    let problem = read_problem(
+        &args.problem.courses,
+        args.problem.commute_gap,
+        &args.problem.weights,
+    );
+    // anything in the files which can't be matched up is as broken as the rest
+    let mut violations = vec![];
+    let solution = problem.read_solutions(&args.problem.solution_paths(&problem), |violation| {
+        violations.push(violation)
+    });
+    let (breakdown, solution_violations) = evaluate_solution(&problem.inputs(None), &solution);
+    violations.extend(solution_violations);
+
+    print_term_totals(&breakdown);
+    println!(
+        "total cost = {}",
+        breakdown.iter().map(|item| item.cost).sum::<u64>()
+    );
+
+    for violation in &violations {
+        println!("violation: {}", describe_violation(&problem, violation));
+    }
+
+    if !args.no_write {
+        for &course in &problem.courses {
+            fs::write(
+                format!("evaluation.{}.tsv", course),
+                tabulate_cost_breakdown(&breakdown, &problem.sessions, &problem.applicants, course),
+            )
+            .unwrap();
+        }
+    }
+
+    if !violations.is_empty() {
+        println!("{} hard constraints broken", violations.len());
+        process::exit(1);
+    }
+}
+
+fn run_repair(args: RepairArgs) {
+    let (problem, solution) = args.problem.read();
+    // including any sessions or tutors which no longer exist
+    let before =
+        file_assignments(&problem.read_solution_files(&args.problem.solution_paths(&problem)));
+
+    // everything still valid is pinned, so the solver can only fill gaps,
+    // and is penalised for each assignment it adds
+    let previous_inputs = problem.inputs(Some(&solution));
+    let kept = keepable_assignments(&previous_inputs);
+    println!("keeping {} of {} assignments", kept.len(), before.len());
+
+    let inputs = SolveInputs {
+        pinned: &kept,
+        ..previous_inputs
+    };
+    let seeds = expand_sequence_specification(&args.seed)
+        .into_iter()
+        .map(|seed| seed as u64)
+        .collect();
+    let options = SolveOptions {
+        steps: Some(args.steps),
+        deadline: None,
+        stagnation_limit: None,
+        cooling: CoolingSchedule::Hyperbolic { multiplier: 1.5 },
+        reheats: 0,
+        restart_from_best: false,
+        check_costs: false,
+        move_probability: 0.15,
+        swap_probability: 0.15,
+        replace_probability: 0.15,
+        alternatives: 0,
+        alternative_distance: 0,
+    };
+    let (repaired, best_seed, _, _) = solve_many_times(seeds, &problem.courses, &inputs, &options);
+
+    let changes = diff_solutions(before, solved_assignments(&repaired));
+    print_solution_diff(&changes);
+
+    if !args.no_write {
+        fs::write(&args.output, tabulate_solution_diff(&changes)).unwrap();
+
+        for &course in &problem.courses {
+            let repaired = repaired
+                .iter()
+                .filter(|assignment| assignment.session.course == course)
+                .cloned()
+                .collect();
+            fs::write(
+                format!("help_sessions.{}.repaired.json", course),
+                convert_to_json_output(repaired, best_seed, course),
+            )
+            .unwrap();
+        }
     }
 }
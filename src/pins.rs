@@ -0,0 +1,98 @@
+use std::{fs, io::ErrorKind};
+
+use crate::{
+    tsv::Tsv,
+    types::{Applicant, Availability, Course, Day, Session, WeekNum},
+};
+
+// A tutor who must be assigned to a session, by index into the sessions and
+// applicants being solved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PinnedAssignment {
+    pub session: usize,
+    pub applicant: usize,
+}
+
+pub fn read_pins(path: &str) -> Option<Tsv> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Some(Tsv::from_string(&contents)),
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            println!("no {path}, not pinning any assignments");
+            None
+        }
+        Err(err) => panic!("couldn't read {path:?}: {err}"),
+    }
+}
+
+// Forbidden assignments are made impossible in the applicants' availabilities,
+// and required ones are returned to be put in every allocation.
+pub fn apply_pins(
+    course: Course,
+    pins: &Tsv,
+    sessions: &[Session],
+    applicants: &mut [Applicant],
+) -> Vec<PinnedAssignment> {
+    let mut must = vec![];
+    let mut forbid = vec![];
+
+    for row in pins {
+        if row.get("Course").parse::<Course>().unwrap() != course {
+            continue;
+        }
+
+        let zid = row.get("zID");
+        let week = WeekNum(row.get("Week").parse().unwrap());
+        let day: Day = row.get("Day").parse().unwrap();
+        let start: u8 = row.get("Start").parse().unwrap();
+
+        let applicant = applicants
+            .iter()
+            .position(|applicant| applicant.zid == zid)
+            .unwrap_or_else(|| panic!("No {course} applicant for pin {:?}", row.all_fields()));
+
+        let mut matching_sessions = sessions.iter().filter(|session| {
+            session.course == course
+                && session.week == week
+                && session.time_24hr == start
+                && session.day == day
+        });
+        let session = matching_sessions
+            .next()
+            .unwrap_or_else(|| panic!("No matching session for pin {:?}", row.all_fields()));
+        if matching_sessions.next().is_some() {
+            panic!("Multiple matching sessions for pin {:?}", row.all_fields())
+        }
+
+        let pin = PinnedAssignment {
+            session: session.id,
+            applicant,
+        };
+        if must.contains(&pin) || forbid.contains(&pin) {
+            panic!("Multiple pins for {zid} in {:?}", session)
+        }
+
+        match row.get("Pin") {
+            "must" => {
+                if applicants[applicant].availabilities[session.id] == Availability::Impossible {
+                    panic!("{zid} is pinned to {:?} but can't do it", session)
+                }
+                must.push(pin);
+            }
+            "forbid" => forbid.push(pin),
+            other => panic!("bad pin {other:?} (should be must or forbid)"),
+        }
+    }
+
+    for session in sessions {
+        let num_pinned = must.iter().filter(|pin| pin.session == session.id).count();
+        if num_pinned > session.max_tutors() {
+            panic!("{num_pinned} tutors pinned to {:?}", session)
+        }
+    }
+
+    for pin in forbid {
+        applicants[pin.applicant].availabilities[pin.session] = Availability::Impossible;
+    }
+
+    must
+}
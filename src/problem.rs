@@ -0,0 +1,294 @@
+use std::fs;
+
+use itertools::Itertools;
+
+use crate::{
+    cost_weights::{read_cost_weights, CostWeights},
+    pins::{apply_pins, read_pins, PinnedAssignment},
+    read_responses::extract_applicants_from_tsv,
+    read_sessions::{
+        apply_priorities, extract_desired_hours, find_session_conflicts, read_sessions_from_string,
+    },
+    solution_output::{
+        match_previous_solution, read_json_output, read_toml_output, DeserialisedSession,
+        SolvedSession,
+    },
+    solver::{HourCount, SolveInputs, Violation},
+    tsv::Tsv,
+    types::{Applicant, Availability, Course, Day, Session, WeekNum},
+};
+
+// Everything read in from the input files for some courses
+pub struct Problem {
+    pub courses: Vec<Course>,
+    pub sessions: Vec<Session>,
+    pub conflicts: Vec<Vec<usize>>,
+    pub desired_hours: Vec<(Course, WeekNum, HourCount)>,
+    pub applicants: Vec<Applicant>,
+    pub pinned: Vec<PinnedAssignment>,
+    pub weights: CostWeights,
+}
+
+impl Problem {
+    pub fn read(courses: Vec<Course>, commute_gap: u8, weights_path: &str) -> Self {
+        let sessions = {
+            let mut sessions =
+                read_sessions_from_string(&fs::read_to_string("sessions.txt").unwrap(), &courses);
+            let priorities = Tsv::from_string(&fs::read_to_string("priorities.tsv").unwrap());
+            for &course in &courses {
+                apply_priorities(course, &priorities, &mut sessions);
+            }
+            sessions
+        };
+
+        println!("{} sessions to schedule", sessions.len());
+
+        let conflicts = find_session_conflicts(&sessions, commute_gap);
+        println!(
+            "{} pairs of sessions can't share a tutor",
+            conflicts.iter().map(Vec::len).sum::<usize>() / 2
+        );
+
+        let responses = Tsv::from_string(&fs::read_to_string("responses.tsv").unwrap());
+        println!("{} form responses", responses.num_rows());
+
+        let desired_hours_tsv = Tsv::from_string(&fs::read_to_string("desired_hours.tsv").unwrap());
+        let desired_hours = courses
+            .iter()
+            .flat_map(|&course| extract_desired_hours(&desired_hours_tsv, course))
+            .collect::<Vec<_>>();
+
+        // a course solved on its own only gets its own tutors, so that shared
+        // tutors aren't double-booked by solving each course separately. Solved
+        // together, anyone who can staff one of them is included, counted
+        // under the first they listed if their primary course isn't solved.
+        let mut applicants = extract_applicants_from_tsv(responses, &sessions)
+            .into_iter()
+            .filter_map(|mut applicant| {
+                if courses.len() == 1 {
+                    return (applicant.course == courses[0]).then_some(applicant);
+                }
+                applicant.course = applicant
+                    .courses
+                    .iter()
+                    .copied()
+                    .find(|course| courses.contains(course))?;
+                Some(applicant)
+            })
+            .collect::<Vec<_>>();
+
+        let pinned = match read_pins("pins.tsv") {
+            Some(pins) => courses
+                .iter()
+                .flat_map(|&course| apply_pins(course, &pins, &sessions, &mut applicants))
+                .collect(),
+            None => vec![],
+        };
+        println!("{} pinned assignments", pinned.len());
+
+        Problem {
+            courses,
+            sessions,
+            conflicts,
+            desired_hours,
+            applicants,
+            pinned,
+            weights: read_cost_weights(weights_path),
+        }
+    }
+
+    pub fn inputs<'a>(&'a self, previous: Option<&'a [SolvedSession]>) -> SolveInputs<'a> {
+        SolveInputs {
+            applicants: &self.applicants,
+            sessions: &self.sessions,
+            desired_hours: &self.desired_hours,
+            weights: &self.weights,
+            previous,
+            pinned: &self.pinned,
+            conflicts: &self.conflicts,
+            frozen_weeks: &[],
+        }
+    }
+
+    // Frozen sessions have already happened, so their tutors in the solution
+    // at `paths` stay even if they've since said they can't make it. Anyone
+    // who has left the responses altogether is kept as a placeholder who can
+    // only do their frozen sessions, so that those weeks (and their hours)
+    // stay as they happened.
+    pub fn allow_frozen_assignments(&mut self, frozen_weeks: &[WeekNum], paths: &[String]) {
+        let solution_files = self.read_solution_files(paths);
+        let missing_tutors = solution_files
+            .iter()
+            .flat_map(|(course, sessions)| {
+                sessions
+                    .iter()
+                    .filter(|session| frozen_weeks.contains(&WeekNum(session.week)))
+                    .flat_map(move |session| {
+                        session
+                            .instructor_zids
+                            .iter()
+                            .map(move |zid| (zid, *course))
+                    })
+            })
+            .filter(|&(zid, _)| {
+                !self
+                    .applicants
+                    .iter()
+                    .any(|applicant| &applicant.zid == zid)
+            })
+            .unique_by(|&(zid, _)| zid)
+            .collect::<Vec<_>>();
+        for &(zid, course) in &missing_tutors {
+            println!(
+                "warning: {zid} isn't in the responses any more, keeping them in frozen weeks only"
+            );
+            self.applicants.push(Applicant {
+                id: self.applicants.len() as u32,
+                email: format!("{zid}@ad.unsw.edu.au"),
+                name: zid.clone(),
+                zid: zid.clone(),
+                course,
+                courses: vec![course],
+                // enough for any frozen sessions, which aren't capped anyway
+                max_hours_per_week: 24 * 7,
+                requested_hours_per_week: 0,
+                availabilities: vec![Availability::Impossible; self.sessions.len()],
+                min_hours_per_week: None,
+                max_hours_per_term: None,
+                min_hours_per_term: None,
+                max_hours_per_day: None,
+                day_preference: None,
+            });
+        }
+
+        // anything which can't be matched up is warned about when it's read again
+        for assignment in self.read_solutions(paths, |_| ()) {
+            let session = &assignment.session;
+            if !frozen_weeks.contains(&session.week) {
+                continue;
+            }
+
+            for previous_applicant in &assignment.applicants {
+                let left = missing_tutors
+                    .iter()
+                    .any(|&(zid, _)| zid == &previous_applicant.zid);
+                let applicant = self
+                    .applicants
+                    .iter_mut()
+                    .find(|applicant| applicant.zid == previous_applicant.zid)
+                    .unwrap();
+                let availability = &mut applicant.availabilities[session.id];
+                if *availability == Availability::Impossible {
+                    if !left {
+                        println!(
+                            "warning: {} can't make frozen week {} {} {}:00 any more, keeping them",
+                            applicant.zid,
+                            session.week.0,
+                            session.day.long_name(),
+                            session.time_24hr
+                        );
+                    }
+                    *availability = Availability::Possible;
+                }
+            }
+        }
+    }
+
+    // Reads previously output help_sessions JSON (or TOML) files, keeping only
+    // the courses being solved
+    pub fn read_solution_files(&self, paths: &[String]) -> Vec<(Course, Vec<DeserialisedSession>)> {
+        paths
+            .iter()
+            .flat_map(|path| {
+                let contents = fs::read_to_string(path).unwrap();
+                if path.ends_with(".toml") {
+                    read_toml_output(&contents, self.course_of_path(path))
+                } else {
+                    read_json_output(&contents)
+                }
+            })
+            .filter(|(course, _)| self.courses.contains(course))
+            .collect()
+    }
+
+    // As for `read_solution_files`, matched up with the sessions and
+    // applicants, with anything which can't be passed to `unmatched`
+    pub fn read_solutions(
+        &self,
+        paths: &[String],
+        mut unmatched: impl FnMut(Violation),
+    ) -> Vec<SolvedSession> {
+        self.read_solution_files(paths)
+            .into_iter()
+            .flat_map(|(course, previous_output)| {
+                match_previous_solution(
+                    course,
+                    &previous_output,
+                    &self.sessions,
+                    &self.applicants,
+                    &mut unmatched,
+                )
+            })
+            .collect()
+    }
+
+    // TOML solutions don't say which course they're for, so it has to come
+    // from the file name unless there's only one course
+    fn course_of_path(&self, path: &str) -> Course {
+        match self.courses[..] {
+            [course] => course,
+            _ => self
+                .courses
+                .iter()
+                .copied()
+                .find(|course| path.contains(&course.to_string()))
+                .unwrap_or_else(|| panic!("can't tell which course {path} is for")),
+        }
+    }
+
+    // The solution files written for each course by default
+    pub fn default_solution_paths(&self) -> Vec<String> {
+        self.courses
+            .iter()
+            .map(|course| format!("help_sessions.{course}.json"))
+            .collect()
+    }
+
+    pub fn find_applicant(&self, zid: &str) -> usize {
+        self.applicants
+            .iter()
+            .position(|applicant| applicant.zid == zid)
+            .unwrap_or_else(|| panic!("{zid} isn't an applicant for {:?}", self.courses))
+    }
+
+    // `course` can be left out when only one course is being solved
+    pub fn find_session(
+        &self,
+        course: Option<Course>,
+        week: WeekNum,
+        day: Day,
+        start: u8,
+    ) -> usize {
+        let mut matching_sessions = self.sessions.iter().filter(|session| {
+            course.is_none_or(|course| session.course == course)
+                && session.week == week
+                && session.day == day
+                && session.time_24hr == start
+        });
+        let session = matching_sessions.next().unwrap_or_else(|| {
+            panic!(
+                "no session in week {} on {} at {start}:00",
+                week.0,
+                day.long_name()
+            )
+        });
+        if matching_sessions.next().is_some() {
+            panic!(
+                "several sessions in week {} on {} at {start}:00 (give the course?)",
+                week.0,
+                day.long_name()
+            )
+        }
+        session.id
+    }
+}
@@ -1,8 +1,10 @@
 use std::str::FromStr;
 
+use itertools::Itertools;
+
 use crate::{
     tsv::Tsv,
-    types::{Applicant, Availability, Session, Venue},
+    types::{Applicant, Availability, Course, DayPreference, Session, Venue},
 };
 
 impl FromStr for Availability {
@@ -40,12 +42,27 @@ pub fn extract_applicants_from_tsv(tsv: Tsv, sessions: &[Session]) -> Vec<Applic
             let name = row.get("Name");
             let course_raw = row.get("Which course are you primarily teaching?");
             let course = course_raw.parse().unwrap();
+            // the primary course, followed by any others they can staff
+            let courses = std::iter::once(course)
+                .chain(
+                    row.get_optional("Which other courses can you teach?")
+                        .unwrap_or_default()
+                        .split(';')
+                        .filter(|s| !s.is_empty())
+                        .map(|other| {
+                            other
+                                .parse()
+                                .unwrap_or_else(|()| panic!("bad course {other:?}"))
+                        }),
+                )
+                .unique()
+                .collect::<Vec<Course>>();
             let raw_hours_request =
                 row.get("Around how many hours would you like to work on help sessions, per week?");
-            let max_hours_per_week = match raw_hours_request {
-                "1-5" => 5,
-                "6-10" => 10,
-                ">10" => 14,
+            let (max_hours_per_week, requested_hours_per_week) = match raw_hours_request {
+                "1-5" => (5, 3),
+                "6-10" => (10, 8),
+                ">10" => (14, 12),
                 _ => panic!("bad max hours {raw_hours_request:?}"),
             };
             let raw_min_hours = row.get("Min hours");
@@ -54,6 +71,32 @@ pub fn extract_applicants_from_tsv(tsv: Tsv, sessions: &[Session]) -> Vec<Applic
             } else {
                 Some(raw_min_hours.parse().unwrap())
             };
+            let optional_hours = |column| {
+                row.get_optional(column)
+                    .filter(|raw_hours| !raw_hours.is_empty())
+                    .map(|raw_hours| {
+                        raw_hours
+                            .parse()
+                            .unwrap_or_else(|_| panic!("bad {column} {raw_hours:?}"))
+                    })
+            };
+            let max_hours_per_day = optional_hours("Max hours per day");
+            let max_hours_per_term = optional_hours("Max hours per term");
+            let min_hours_per_term = optional_hours("Min hours per term");
+            if let (Some(min), Some(max)) = (min_hours_per_term, max_hours_per_term) {
+                if min > max {
+                    panic!("{zid} has more min hours per term than max");
+                }
+            }
+            let day_preference = match row
+                .get_optional("Would you like your sessions on the same day or spread out?")
+                .unwrap_or_default()
+            {
+                "Same day" => Some(DayPreference::Cluster),
+                "Spread out" => Some(DayPreference::Spread),
+                "" | "No preference" => None,
+                other => panic!("bad day preference {other:?}"),
+            };
             let cant_do_weeks = row
                 .get("Are then any weeks you specifically are not available?")
                 .split(';')
@@ -69,7 +112,8 @@ pub fn extract_applicants_from_tsv(tsv: Tsv, sessions: &[Session]) -> Vec<Applic
             let availabilities = sessions
                 .iter()
                 .map(|session| {
-                    if cant_do_weeks.contains(&session.week.0) {
+                    if cant_do_weeks.contains(&session.week.0) || !courses.contains(&session.course)
+                    {
                         return Availability::Impossible;
                     }
 
@@ -97,9 +141,15 @@ pub fn extract_applicants_from_tsv(tsv: Tsv, sessions: &[Session]) -> Vec<Applic
                 zid: zid.into(),
                 name: name.into(),
                 course,
+                courses,
                 max_hours_per_week,
+                requested_hours_per_week,
                 availabilities,
                 min_hours_per_week: min_hours,
+                max_hours_per_term,
+                min_hours_per_term,
+                max_hours_per_day,
+                day_preference,
             }
         })
         .collect()
@@ -1,6 +1,5 @@
 use core::panic;
-
-use itertools::Itertools;
+use std::collections::HashSet;
 
 use crate::{
     tsv::Tsv,
@@ -35,7 +34,12 @@ fn twelve_hour_to_twentfour_hour(s: &str) -> Option<u8> {
     }
 }
 
-fn sessions_from_specification_line(line: &str, id: &mut usize) -> Vec<Session> {
+fn sessions_from_specification_line(
+    line: &str,
+    course: Course,
+    id: &mut usize,
+    slot: &mut usize,
+) -> Vec<Session> {
     let without_comment = line
         .split_once('#')
         .map(|(before, _)| before)
@@ -46,10 +50,23 @@ fn sessions_from_specification_line(line: &str, id: &mut usize) -> Vec<Session>
         return vec![];
     }
 
-    let (day, time, length, venue, weeks, location) = without_comment
-        .split_whitespace()
-        .collect_tuple()
-        .unwrap_or_else(|| panic!("bad session line: {line:?}"));
+    // the last field, the most tutors who can fit, is optional
+    let fields = without_comment.split_whitespace().collect::<Vec<_>>();
+    let (day, time, length, venue, weeks, location, max_allocation) = match fields[..] {
+        [day, time, length, venue, weeks, location] => {
+            (day, time, length, venue, weeks, location, None)
+        }
+        [day, time, length, venue, weeks, location, max_allocation] => (
+            day,
+            time,
+            length,
+            venue,
+            weeks,
+            location,
+            Some(max_allocation),
+        ),
+        _ => panic!("bad session line: {line:?}"),
+    };
 
     let day = day.parse().unwrap_or_else(|err| panic!("{err}: {line:?}"));
     let time = twelve_hour_to_twentfour_hour(time)
@@ -61,6 +78,14 @@ fn sessions_from_specification_line(line: &str, id: &mut usize) -> Vec<Session>
     let venue = venue
         .parse()
         .unwrap_or_else(|()| panic!("bad vanue on {line:?}"));
+    let max_allocation = max_allocation.map(|max_allocation| {
+        max_allocation
+            .parse()
+            .unwrap_or_else(|_| panic!("bad max allocation {max_allocation:?} on line {line:?}"))
+    });
+
+    *slot += 1;
+    let slot = *slot - 1;
 
     expand_sequence_specification(weeks)
         .into_iter()
@@ -76,33 +101,46 @@ fn sessions_from_specification_line(line: &str, id: &mut usize) -> Vec<Session>
             length_hours: length,
             location: location.replace('_', " "),
             min_allocation: None,
+            max_allocation,
+            course,
+            slot,
         })
         .collect::<Vec<_>>()
 }
 
-pub fn read_sessions_from_string(input: &str) -> Vec<Session> {
+// Every course runs the same sessions, so they're repeated for each course
+pub fn read_sessions_from_string(input: &str, courses: &[Course]) -> Vec<Session> {
     let mut id = 0;
-
-    input
-        .lines()
-        .flat_map(|line| sessions_from_specification_line(line, &mut id))
+    let mut slot = 0;
+
+    courses
+        .iter()
+        .flat_map(|&course| {
+            input
+                .lines()
+                .flat_map(|line| sessions_from_specification_line(line, course, &mut id, &mut slot))
+                .collect::<Vec<_>>()
+        })
         .collect()
 }
 
-pub fn extract_desired_hours(tsv: Tsv, course: Course) -> Vec<(WeekNum, u16)> {
+pub fn extract_desired_hours(tsv: &Tsv, course: Course) -> Vec<(Course, WeekNum, u16)> {
     tsv.into_iter()
         .map(|row| {
             let week = row.get("Week").parse().unwrap();
             let hours = row
-                .get(&format!("Desired {} hours", course.to_string()))
+                .get(&format!("Desired {} hours", course))
                 .parse()
                 .unwrap();
-            (WeekNum(week), hours)
+            (course, WeekNum(week), hours)
         })
         .collect()
 }
 
 pub fn apply_priorities(course: Course, priorities: &Tsv, sessions: &mut [Session]) {
+    // rows can leave out the minimum, so it can't be used to spot repeats
+    let mut seen_sessions = HashSet::new();
+
     for row in priorities {
         if row.get("Course").parse::<Course>().unwrap() != course {
             continue;
@@ -112,10 +150,17 @@ pub fn apply_priorities(course: Course, priorities: &Tsv, sessions: &mut [Sessio
         let day: Day = row.get("Day").parse().unwrap();
         let venue: Venue = row.get("Venue").parse().unwrap();
         let start: u8 = row.get("Start").parse().unwrap();
-        let minimum: u16 = row.get("Minimum").parse().unwrap();
+        let minimum = Some(row.get("Minimum"))
+            .filter(|minimum| !minimum.is_empty())
+            .map(|minimum| minimum.parse::<u16>().unwrap());
+        let maximum = row
+            .get_optional("Maximum")
+            .filter(|maximum| !maximum.is_empty())
+            .map(|maximum| maximum.parse::<u16>().unwrap());
 
         let mut matching_sesions = sessions.iter_mut().filter(|session| {
-            session.week == week
+            session.course == course
+                && session.week == week
                 && session.venue == venue
                 && session.time_24hr == start
                 && session.day == day
@@ -129,10 +174,32 @@ pub fn apply_priorities(course: Course, priorities: &Tsv, sessions: &mut [Sessio
                 row.all_fields()
             )
         }
-        if session.min_allocation.is_some() {
+        if !seen_sessions.insert(session.id) {
             panic!("Multiple priorities for {:?}", session)
         }
 
-        session.min_allocation = Some(minimum);
+        session.min_allocation = minimum;
+        if maximum.is_some() {
+            session.max_allocation = maximum;
+        }
+        if let (Some(minimum), Some(maximum)) = (session.min_allocation, session.max_allocation) {
+            if minimum > maximum {
+                panic!("Minimum is more than maximum for {:?}", session)
+            }
+        }
     }
 }
+
+// For each session, the other sessions which can't share a tutor with it
+pub fn find_session_conflicts(sessions: &[Session], commute_gap: u8) -> Vec<Vec<usize>> {
+    sessions
+        .iter()
+        .map(|session| {
+            sessions
+                .iter()
+                .filter(|other| other.id != session.id && other.clashes(session, commute_gap))
+                .map(|other| other.id)
+                .collect()
+        })
+        .collect()
+}
@@ -0,0 +1,187 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use itertools::Itertools;
+
+use crate::{
+    solution_output::{DeserialisedSession, SolvedSession},
+    types::{Course, Day, Session, Venue},
+};
+
+// Enough to tell sessions apart between two solution files
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SessionKey {
+    course: Course,
+    week: u8,
+    day: Day,
+    start: u8,
+    length: u8,
+    mode: String,
+    location: String,
+}
+
+impl SessionKey {
+    fn new(course: Course, session: &DeserialisedSession) -> Self {
+        SessionKey {
+            course,
+            week: session.week,
+            day: session
+                .day
+                .parse()
+                .unwrap_or_else(|err| panic!("{err} in solution JSON")),
+            start: session.start_time_24hrs,
+            length: session.duration_hours,
+            mode: session.mode.clone(),
+            location: session.location.clone(),
+        }
+    }
+
+    fn from_session(session: &Session) -> Self {
+        SessionKey {
+            course: session.course,
+            week: session.week.0,
+            day: session.day,
+            start: session.time_24hr,
+            length: session.length_hours,
+            mode: match session.venue {
+                Venue::FaceToFace => "f2f",
+                Venue::Online => "online",
+            }
+            .to_string(),
+            location: session.location.clone(),
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "{} week {} {} {}:00-{}:00 ({})",
+            self.course,
+            self.week,
+            self.day.long_name(),
+            self.start,
+            self.start + self.length,
+            self.location
+        )
+    }
+}
+
+// The sessions a tutor gained and lost between two solutions
+pub struct TutorChange {
+    pub zid: String,
+    pub hours_before: u32,
+    pub hours_after: u32,
+    pub gained: Vec<String>,
+    pub lost: Vec<String>,
+}
+
+impl TutorChange {
+    fn new(zid: &str) -> Self {
+        TutorChange {
+            zid: zid.to_string(),
+            hours_before: 0,
+            hours_after: 0,
+            gained: vec![],
+            lost: vec![],
+        }
+    }
+}
+
+// Each tutor's zid with each of their sessions
+pub type Assignments = BTreeSet<(String, SessionKey)>;
+
+pub fn file_assignments(solution: &[(Course, Vec<DeserialisedSession>)]) -> Assignments {
+    solution
+        .iter()
+        .flat_map(|(course, sessions)| {
+            sessions.iter().flat_map(move |session| {
+                let key = SessionKey::new(*course, session);
+                session
+                    .instructor_zids
+                    .iter()
+                    .map(move |zid| (zid.clone(), key.clone()))
+            })
+        })
+        .collect()
+}
+
+pub fn solved_assignments(solution: &[SolvedSession]) -> Assignments {
+    solution
+        .iter()
+        .flat_map(|assignment| {
+            let key = SessionKey::from_session(&assignment.session);
+            assignment
+                .applicants
+                .iter()
+                .map(move |applicant| (applicant.zid.clone(), key.clone()))
+        })
+        .collect()
+}
+
+// Every tutor whose sessions differ between `before` and `after`, by zid
+pub fn diff_solutions(before: Assignments, after: Assignments) -> Vec<TutorChange> {
+    let mut changes: BTreeMap<&str, TutorChange> = BTreeMap::new();
+
+    for (zid, session) in &before {
+        let change = changes.entry(zid).or_insert_with(|| TutorChange::new(zid));
+        change.hours_before += session.length as u32;
+        if !after.contains(&(zid.clone(), session.clone())) {
+            change.lost.push(session.describe());
+        }
+    }
+    for (zid, session) in &after {
+        let change = changes.entry(zid).or_insert_with(|| TutorChange::new(zid));
+        change.hours_after += session.length as u32;
+        if !before.contains(&(zid.clone(), session.clone())) {
+            change.gained.push(session.describe());
+        }
+    }
+
+    changes
+        .into_values()
+        .filter(|change| !change.gained.is_empty() || !change.lost.is_empty())
+        .collect()
+}
+
+pub fn print_solution_diff(changes: &[TutorChange]) {
+    if changes.is_empty() {
+        println!("no tutors' sessions changed");
+        return;
+    }
+
+    for change in changes {
+        println!(
+            "{}: {} -> {} hours ({:+})",
+            change.zid,
+            change.hours_before,
+            change.hours_after,
+            (change.hours_after as i64) - (change.hours_before as i64)
+        );
+        for session in &change.gained {
+            println!("  gained {session}");
+        }
+        for session in &change.lost {
+            println!("  lost   {session}");
+        }
+    }
+    println!("{} tutors' sessions changed", changes.len());
+}
+
+// One row per tutor, for mail merging change notices
+pub fn tabulate_solution_diff(changes: &[TutorChange]) -> String {
+    let mut result = String::new();
+
+    result.push_str("zid\tHours before\tHours after\tChange\tGained\tLost\n");
+
+    for change in changes {
+        result.push_str(&format!(
+            "{}\t{}\t{}\t{:+}\t{}\t{}\n",
+            change.zid,
+            change.hours_before,
+            change.hours_after,
+            (change.hours_after as i64) - (change.hours_before as i64),
+            change.gained.iter().join("; "),
+            change.lost.iter().join("; ")
+        ));
+    }
+
+    result
+}
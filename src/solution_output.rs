@@ -1,12 +1,13 @@
 use std::collections::{HashMap, HashSet};
 
 use itertools::Itertools;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 
 use crate::{
-    solver::Seed,
-    types::{Applicant, Availability, Course, Session, Venue, WeekNum},
+    read_sessions::expand_sequence_specification,
+    solver::{Cost, CostItem, Seed, Violation},
+    types::{Applicant, Availability, Course, Day, Session, Venue, WeekNum},
 };
 
 #[derive(Debug, Clone)]
@@ -18,7 +19,7 @@ pub struct SolvedSession {
 pub fn tabulate_solution_info(mut solution: Vec<SolvedSession>) -> String {
     println!("Solved for {} sessions", solution.len());
 
-    solution.sort_by_key(|assignment| (assignment.session.week.0));
+    solution.sort_by_key(|assignment| assignment.session.week.0);
 
     let mut hours_by_week: HashMap<WeekNum, u32> = HashMap::new();
     let mut preference_totals: HashMap<Availability, u32> = HashMap::new();
@@ -84,10 +85,16 @@ pub fn tabulate_solution_info(mut solution: Vec<SolvedSession>) -> String {
     output
 }
 
-pub fn tabulate_hours_by_tutor(solution: Vec<SolvedSession>) -> String {
+// Every tutor in `applicants` is included, even if they weren't given any hours
+pub fn tabulate_hours_by_tutor(solution: Vec<SolvedSession>, applicants: &[Applicant]) -> String {
     let mut totals: HashMap<String, HashMap<WeekNum, u32>> = HashMap::new();
     let mut zid_to_applicant: HashMap<String, Applicant> = HashMap::new();
 
+    for applicant in applicants {
+        zid_to_applicant.insert(applicant.zid.clone(), applicant.clone());
+        totals.entry(applicant.zid.clone()).or_default();
+    }
+
     for assignment in solution {
         let session = &assignment.session;
         for applicant in assignment.applicants {
@@ -103,8 +110,8 @@ pub fn tabulate_hours_by_tutor(solution: Vec<SolvedSession>) -> String {
         }
     }
     let all_weeks = totals
-        .iter()
-        .flat_map(|(_, map_by_week)| map_by_week.keys())
+        .values()
+        .flat_map(|map_by_week| map_by_week.keys())
         .copied()
         .collect::<HashSet<_>>()
         .iter()
@@ -114,21 +121,28 @@ pub fn tabulate_hours_by_tutor(solution: Vec<SolvedSession>) -> String {
 
     let mut result = String::new();
 
-    result.push_str("Name\tzid\tMax hours\tMin hours");
+    result.push_str("Name\tzid\tMax hours\tMin hours\tRequested hours\tFulfilment");
     for week in &all_weeks {
         result.push_str(&format!("\tWeek {}", week.0));
     }
-    result.push('\n');
+    result.push_str("\tTotal\tMax term hours\tMin term hours\n");
 
     for (zid, hours_by_week) in totals.into_iter().sorted_by_key(|(s, _)| s.clone()) {
         let applicant = &zid_to_applicant[&zid];
 
+        // of the hours they asked for across the whole term
+        let fulfilment = (100 * hours_by_week.values().sum::<u32>())
+            .checked_div(applicant.requested_hours_per_week as u32 * all_weeks.len() as u32)
+            .unwrap_or_default();
+
         result.push_str(&format!(
-            "{}\t{}\t{}\t{}",
+            "{}\t{}\t{}\t{}\t{}\t{}%",
             &applicant.name,
             &applicant.zid,
             &applicant.max_hours_per_week,
-            &applicant.min_hours_per_week.unwrap_or_default()
+            &applicant.min_hours_per_week.unwrap_or_default(),
+            &applicant.requested_hours_per_week,
+            fulfilment
         ));
 
         for week in &all_weeks {
@@ -138,7 +152,68 @@ pub fn tabulate_hours_by_tutor(solution: Vec<SolvedSession>) -> String {
             ));
         }
 
-        result.push('\n');
+        result.push_str(&format!(
+            "\t{}\t{}\t{}\n",
+            hours_by_week.values().sum::<u32>(),
+            applicant
+                .max_hours_per_term
+                .map(|hours| hours.to_string())
+                .unwrap_or_default(),
+            applicant
+                .min_hours_per_term
+                .map(|hours| hours.to_string())
+                .unwrap_or_default()
+        ));
+    }
+
+    result
+}
+
+pub fn tabulate_cost_breakdown(
+    items: &[CostItem],
+    sessions: &[Session],
+    applicants: &[Applicant],
+    course: Course,
+) -> String {
+    let mut result = String::new();
+
+    result.push_str("Term\tWeek\tSession\tzid\tName\tCost\n");
+
+    let course_items = items
+        .iter()
+        .filter(|item| item.course == course)
+        .sorted_by_key(|item| (item.term, item.week, item.session, item.applicant));
+
+    for item in course_items {
+        let session = item.session.map(|session_index| {
+            let session = &sessions[session_index];
+            format!(
+                "{} {}:00 {}",
+                session.day.short_name(),
+                session.time_24hr,
+                match session.venue {
+                    Venue::FaceToFace => "f2f",
+                    Venue::Online => "online",
+                }
+            )
+        });
+        let applicant = item
+            .applicant
+            .map(|applicant_index| &applicants[applicant_index]);
+
+        result.push_str(&format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\n",
+            item.term.name(),
+            item.week.map(|week| week.0.to_string()).unwrap_or_default(),
+            session.unwrap_or_default(),
+            applicant
+                .map(|applicant| applicant.zid.as_str())
+                .unwrap_or_default(),
+            applicant
+                .map(|applicant| applicant.name.as_str())
+                .unwrap_or_default(),
+            item.cost
+        ));
     }
 
     result
@@ -229,6 +304,183 @@ struct SerialisedSession {
     location: String,
 }
 
+#[derive(Deserialize)]
+pub struct DeserialisedSession {
+    pub instructor_zids: Vec<String>,
+    pub week: u8,
+    pub day: String,
+    pub start_time_24hrs: u8,
+    pub duration_hours: u8,
+    pub mode: String,
+    pub location: String,
+}
+
+#[derive(Deserialize)]
+struct DeserialisedCourse {
+    #[allow(dead_code)]
+    seed: Seed,
+    sessions: Vec<DeserialisedSession>,
+}
+
+impl DeserialisedSession {
+    fn matches(&self, session: &Session) -> bool {
+        self.week == session.week.0
+            && self.day.parse() == Ok(session.day)
+            && self.start_time_24hrs == session.time_24hr
+            && self.duration_hours == session.length_hours
+            && self.mode.parse() == Ok(session.venue)
+    }
+}
+
+pub fn read_json_output(contents: &str) -> Vec<(Course, Vec<DeserialisedSession>)> {
+    let courses: HashMap<String, DeserialisedCourse> =
+        serde_json::from_str(contents).unwrap_or_else(|err| panic!("bad solution JSON: {err}"));
+    courses
+        .into_iter()
+        .map(|(course, output)| {
+            let course = course
+                .parse()
+                .unwrap_or_else(|()| panic!("bad course {course:?} in solution JSON"));
+            (course, output.sessions)
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct AtciToml {
+    class: AtciClass,
+}
+
+#[derive(Deserialize)]
+struct AtciClass {
+    consult: Vec<AtciConsult>,
+}
+
+#[derive(Deserialize)]
+struct AtciConsult {
+    instructors: Vec<String>,
+    weeks: String,
+    day: String,
+    start: String,
+    end: String,
+    mode: String,
+    location: Option<String>,
+}
+
+fn parse_hour(time: &str) -> u8 {
+    time.strip_suffix(":00")
+        .and_then(|hour| hour.parse().ok())
+        .unwrap_or_else(|| panic!("bad time {time:?} in solution TOML (should be on the hour)"))
+}
+
+// Reads a solution in the format of `output_to_atci_toml`, which doesn't say
+// which course it's for
+pub fn read_toml_output(contents: &str, course: Course) -> Vec<(Course, Vec<DeserialisedSession>)> {
+    let toml: AtciToml =
+        toml::from_str(contents).unwrap_or_else(|err| panic!("bad solution TOML: {err}"));
+
+    let sessions = toml
+        .class
+        .consult
+        .into_iter()
+        .flat_map(|consult| {
+            let day = consult
+                .day
+                .to_lowercase()
+                .parse::<Day>()
+                .unwrap_or_else(|err| panic!("{err} in solution TOML"));
+            let start = parse_hour(&consult.start);
+            let end = parse_hour(&consult.end);
+            let location = consult.location.unwrap_or_else(|| "Online".to_string());
+
+            // hand edits might put several weeks together
+            expand_sequence_specification(&consult.weeks)
+                .into_iter()
+                .map(move |week| DeserialisedSession {
+                    instructor_zids: consult.instructors.clone(),
+                    week: week as u8,
+                    day: day.long_name().to_string(),
+                    start_time_24hrs: start,
+                    duration_hours: end - start,
+                    mode: consult.mode.clone(),
+                    location: location.clone(),
+                })
+        })
+        .collect();
+
+    vec![(course, sessions)]
+}
+
+// Matches a previously output solution up with the current sessions and
+// applicants, skipping anything which no longer exists (which is `unmatched`).
+pub fn match_previous_solution(
+    course: Course,
+    previous: &[DeserialisedSession],
+    sessions: &[Session],
+    applicants: &[Applicant],
+    mut unmatched: impl FnMut(Violation),
+) -> Vec<SolvedSession> {
+    previous
+        .iter()
+        .filter_map(|previous_session| {
+            let Some(session) = sessions
+                .iter()
+                .find(|session| session.course == course && previous_session.matches(session))
+            else {
+                unmatched(Violation::UnknownSession {
+                    description: format!(
+                        "{course} week {} {} {}:00 ({}, {})",
+                        previous_session.week,
+                        previous_session.day,
+                        previous_session.start_time_24hrs,
+                        previous_session.mode,
+                        previous_session.location
+                    ),
+                });
+                return None;
+            };
+
+            let applicants = previous_session
+                .instructor_zids
+                .iter()
+                .filter_map(|zid| {
+                    let applicant = applicants.iter().find(|applicant| &applicant.zid == zid);
+                    if applicant.is_none() {
+                        unmatched(Violation::UnknownTutor {
+                            session: session.id,
+                            zid: zid.clone(),
+                        });
+                    }
+                    applicant.cloned()
+                })
+                .collect();
+
+            Some(SolvedSession {
+                session: session.clone(),
+                applicants,
+            })
+        })
+        .collect()
+}
+
+pub fn count_changed_assignments(before: &[SolvedSession], after: &[SolvedSession]) -> usize {
+    let assignments = |solution: &[SolvedSession]| {
+        solution
+            .iter()
+            .flat_map(|assignment| {
+                assignment
+                    .applicants
+                    .iter()
+                    .map(|applicant| (assignment.session.id, applicant.zid.clone()))
+            })
+            .collect::<HashSet<_>>()
+    };
+
+    assignments(before)
+        .symmetric_difference(&assignments(after))
+        .count()
+}
+
 impl From<&SolvedSession> for SerialisedSession {
     fn from(assignment: &SolvedSession) -> Self {
         let session = &assignment.session;
@@ -253,29 +505,47 @@ impl From<&SolvedSession> for SerialisedSession {
     }
 }
 
-pub fn convert_to_json_output(
-    mut solution: Vec<SolvedSession>,
-    seed: Seed,
-    course: Course,
-) -> String {
+fn serialise_sessions(mut solution: Vec<SolvedSession>) -> serde_json::Value {
     solution.sort_by_key(|assignment| {
         let session = &assignment.session;
         (session.week, session.day, session.time_24hr)
     });
 
-    let session_array = serde_json::to_value(
+    serde_json::to_value(
         solution
             .iter()
             .filter(|session| !session.applicants.is_empty())
             .map(SerialisedSession::from)
             .collect::<Vec<_>>(),
     )
-    .unwrap();
+    .unwrap()
+}
+
+pub fn convert_to_json_output(solution: Vec<SolvedSession>, seed: Seed, course: Course) -> String {
+    json!({
+        course.to_string(): {
+            "seed": seed,
+            "sessions": serialise_sessions(solution)
+        }
+    })
+    .to_string()
+}
 
+// As for `convert_to_json_output`, but noting how the alternative compares to
+// the best solution (`difference_from_best` being for this course only)
+pub fn convert_alternative_to_json_output(
+    solution: Vec<SolvedSession>,
+    seed: Seed,
+    course: Course,
+    cost: Cost,
+    difference_from_best: usize,
+) -> String {
     json!({
         course.to_string(): {
             "seed": seed,
-            "sessions": session_array
+            "cost": cost,
+            "difference_from_best": difference_from_best,
+            "sessions": serialise_sessions(solution)
         }
     })
     .to_string()
@@ -2,58 +2,776 @@ use core::panic;
 use std::{
     cmp::{max, min},
     collections::HashSet,
+    time::Instant,
 };
 
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use smallvec::SmallVec;
+use fastrand::Rng;
+use itertools::Itertools;
+use rayon::iter::{
+    IndexedParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator,
+};
+use smallvec::{smallvec, SmallVec};
 
 use crate::{
+    cooling::CoolingSchedule,
+    cost_weights::CostWeights,
+    exact::solve_relaxation,
+    pins::PinnedAssignment,
     solution_output::SolvedSession,
-    types::{Applicant, Availability, Course, Session, WeekNum},
+    types::{
+        Applicant, Availability, Course, Day, DayPreference, Session, WeekNum,
+        DEFAULT_MAX_ALLOCATION,
+    },
 };
 
-const MAX_TUTORS_PER_SESSION: usize = 5;
 type ApplicantId = u16;
-type HourCount = u16;
-type Cost = u64;
+pub type HourCount = u16;
+pub type Cost = u64;
 pub type Seed = u64;
 
 #[derive(Debug, Clone)]
 struct SessionAllocation {
-    assigned: SmallVec<[ApplicantId; MAX_TUTORS_PER_SESSION]>,
+    assigned: SmallVec<[ApplicantId; DEFAULT_MAX_ALLOCATION]>,
+}
+
+impl SessionAllocation {
+    // The number of tutors in one allocation but not the other
+    fn num_differences(&self, other: &SessionAllocation) -> usize {
+        self.assigned
+            .iter()
+            .filter(|applicant| !other.assigned.contains(applicant))
+            .count()
+            + other
+                .assigned
+                .iter()
+                .filter(|applicant| !self.assigned.contains(applicant))
+                .count()
+    }
+}
+
+// The number of tutor-session assignments which differ between two allocations
+fn assignment_distance(first: &[SessionAllocation], second: &[SessionAllocation]) -> usize {
+    first
+        .iter()
+        .zip(second)
+        .map(|(first, second)| first.num_differences(second))
+        .sum()
 }
 
+// The sessions for one course in one week
 struct Week {
+    course: Course,
+    week_num: WeekNum,
     desired_total_hours: HourCount,
     session_indexes: Vec<usize>,
+    // the largest any of its sessions can be
+    max_size: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum CostTerm {
+    Preference,
+    MinAllocationShortfall,
+    Change,
+    MinHoursShortfall,
+    UnderTarget,
+    OverTarget,
+    SizeImbalance,
+    DayPreference,
+    TermMinimumShortfall,
+    Continuity,
+    Fairness,
+}
+
+impl CostTerm {
+    pub const ALL: [CostTerm; 11] = [
+        CostTerm::Preference,
+        CostTerm::MinAllocationShortfall,
+        CostTerm::Change,
+        CostTerm::MinHoursShortfall,
+        CostTerm::UnderTarget,
+        CostTerm::OverTarget,
+        CostTerm::SizeImbalance,
+        CostTerm::DayPreference,
+        CostTerm::TermMinimumShortfall,
+        CostTerm::Continuity,
+        CostTerm::Fairness,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CostTerm::Preference => "preference",
+            CostTerm::MinAllocationShortfall => "min allocation shortfall",
+            CostTerm::Change => "change from previous",
+            CostTerm::MinHoursShortfall => "min hours shortfall",
+            CostTerm::UnderTarget => "under target",
+            CostTerm::OverTarget => "over target",
+            CostTerm::SizeImbalance => "size imbalance",
+            CostTerm::DayPreference => "day preference",
+            CostTerm::TermMinimumShortfall => "term minimum shortfall",
+            CostTerm::Continuity => "continuity",
+            CostTerm::Fairness => "fairness",
+        }
+    }
+}
+
+// One contribution to the total cost. Terms about a tutor rather than a
+// session are put under the tutor's primary course.
+#[derive(Debug, Clone)]
+pub struct CostItem {
+    pub term: CostTerm,
+    pub course: Course,
+    pub week: Option<WeekNum>,
+    pub session: Option<usize>,
+    pub applicant: Option<usize>,
+    pub cost: Cost,
 }
 
 struct Solver<'a> {
     sessions: &'a [Session],
     applicants: &'a [Applicant],
+    weights: &'a CostWeights,
     weeks: Vec<Week>,
+    session_weeks: Vec<usize>,
+    // hour caps apply across all courses, so applicants' hours are totalled
+    // by calendar week rather than by `Week`
+    calendar_weeks: Vec<WeekNum>,
+    session_calendar_weeks: Vec<usize>,
+    // and by day, for the daily limits and preferences
+    calendar_days: Vec<(WeekNum, Day)>,
+    calendar_week_days: Vec<Vec<usize>>,
+    session_calendar_days: Vec<usize>,
+    // sessions which clash with each session, and so can't share any tutors
+    session_conflicts: &'a [Vec<usize>],
+    // applicants who aren't marked as impossible for each session
+    session_candidates: Vec<Vec<ApplicantId>>,
+    // empty (and no penalty) unless warm starting from a previous solution
+    previous_assignments: Vec<SmallVec<[ApplicantId; DEFAULT_MAX_ALLOCATION]>>,
+    change_penalty: Cost,
+    // the previous and next weeks' sessions in the same recurring slot
+    previous_occurrences: Vec<Option<usize>>,
+    next_occurrences: Vec<Option<usize>>,
+    // the tutors of each course are compared with each other for fairness
+    fairness_groups: Vec<FairnessGroup>,
+    applicant_fairness_groups: Vec<usize>,
+    // applicants who must stay assigned to each session
+    pinned: Vec<SmallVec<[ApplicantId; DEFAULT_MAX_ALLOCATION]>>,
+    // sessions outside the frozen weeks, which mutations can change
+    open_sessions: Vec<usize>,
+}
+
+struct FairnessGroup {
+    course: Course,
+    total_requested: u64,
+    sum_squared_requested: u64,
+}
+
+// Sums over the tutors in a fairness group, from which the fairness cost can
+// be found exactly without going through every tutor
+#[derive(Debug, Clone, Copy, Default)]
+struct FairnessSums {
+    hours: u64,
+    squared_hours: u64,
+    hours_times_requested: u64,
+}
+
+impl FairnessSums {
+    fn add_tutor(&mut self, hours: HourCount, requested: HourCount) {
+        let (hours, requested) = (hours as u64, requested as u64);
+        self.hours += hours;
+        self.squared_hours += hours * hours;
+        self.hours_times_requested += hours * requested;
+    }
+
+    fn remove_tutor(&mut self, hours: HourCount, requested: HourCount) {
+        let (hours, requested) = (hours as u64, requested as u64);
+        self.hours -= hours;
+        self.squared_hours -= hours * hours;
+        self.hours_times_requested -= hours * requested;
+    }
+}
+
+pub struct SolveInputs<'a> {
+    // only those applicants who could be assigned
+    pub applicants: &'a [Applicant],
+    pub sessions: &'a [Session],
+    pub desired_hours: &'a [(Course, WeekNum, HourCount)],
+    pub weights: &'a CostWeights,
+    // a previous solution to start from, penalising changes to it
+    pub previous: Option<&'a [SolvedSession]>,
+    pub pinned: &'a [PinnedAssignment],
+    // from `find_session_conflicts`
+    pub conflicts: &'a [Vec<usize>],
+    // weeks whose sessions keep exactly their tutors from `previous`
+    pub frozen_weeks: &'a [WeekNum],
+}
+
+#[derive(Clone)]
+pub struct SolveOptions {
+    // solving stops at whichever of these comes first
+    pub steps: Option<u64>,
+    pub deadline: Option<Instant>,
+    pub stagnation_limit: Option<u64>,
+    pub cooling: CoolingSchedule,
+    // the budget is split into this many + 1 cycles, each starting hot again
+    pub reheats: u32,
+    // whether each reheat starts from the best allocation found so far
+    pub restart_from_best: bool,
+    pub check_costs: bool,
+    // adding and removing single applicants share whatever is left over
+    pub move_probability: f32,
+    pub swap_probability: f32,
+    pub replace_probability: f32,
+    // how many other solutions to keep besides the best, and how many
+    // assignments each has to differ by from the best and each other
+    pub alternatives: usize,
+    pub alternative_distance: usize,
+}
+
+impl SolveOptions {
+    // The fraction of the time budget used by a solve that started at `start`
+    fn time_progress(&self, start: Instant) -> f32 {
+        match self.deadline {
+            Some(deadline) => {
+                let total = deadline.saturating_duration_since(start).as_secs_f32();
+                if total > 0.0 {
+                    start.elapsed().as_secs_f32() / total
+                } else {
+                    1.0
+                }
+            }
+            None => 0.0,
+        }
+    }
+
+    fn budget_exhausted(
+        &self,
+        time_progress: f32,
+        step: u64,
+        steps_since_improvement: u64,
+    ) -> bool {
+        time_progress >= 1.0
+            || self.steps.is_some_and(|steps| step >= steps)
+            || self
+                .stagnation_limit
+                .is_some_and(|limit| steps_since_improvement >= limit)
+    }
+
+    fn step_progress(&self, step: u64) -> f32 {
+        match self.steps {
+            Some(steps) => (step as f32) / (steps as f32),
+            None => 0.0,
+        }
+    }
+}
+
+// A good solution which is usefully different from the best one
+pub struct Alternative {
+    pub solution: Vec<SolvedSession>,
+    pub seed: Seed,
+    pub cost: Cost,
+    // the number of tutor-session assignments differing from the best solution
+    pub distance: usize,
+}
+
+pub struct TemperingOptions {
+    pub min_temperature: f32,
+    pub max_temperature: f32,
+    // steps each replica takes between attempts to exchange states
+    pub exchange_interval: u64,
+}
+
+// The allocation along with running totals, so that the cost of a single
+// change can be found without re-evaluating everything.
+#[derive(Debug, Clone)]
+struct AllocationState {
+    allocations: Vec<SessionAllocation>,
+    applicant_weekly_totals: Vec<Vec<HourCount>>,
+    applicant_daily_totals: Vec<Vec<HourCount>>,
+    applicant_daily_sessions: Vec<Vec<u16>>,
+    applicant_overall_totals: Vec<HourCount>,
+    weekly_effective_hours: Vec<HourCount>,
+    // number of non-priority sessions of each size, for the imbalance penalty
+    // (indexed by size, up to the week's `max_size`)
+    weekly_size_counts: Vec<Vec<u16>>,
+    fairness_sums: Vec<FairnessSums>,
+    cost: Cost,
 }
 
 #[derive(Debug)]
-#[allow(dead_code)]
 enum Mutation {
-    AddToSession { session: usize, applicant: u16 },
-    RemoveFromSession { session: usize, applicant: u16 },
+    AddToSession {
+        session: usize,
+        applicant: ApplicantId,
+    },
+    RemoveFromSession {
+        session: usize,
+        applicant: ApplicantId,
+    },
+    Move {
+        from: usize,
+        to: usize,
+        applicant: ApplicantId,
+    },
+    Swap {
+        first: (usize, ApplicantId),
+        second: (usize, ApplicantId),
+    },
+    Replace {
+        session: usize,
+        old_applicant: ApplicantId,
+        new_applicant: ApplicantId,
+    },
+}
+
+impl Mutation {
+    // The individual (session, applicant, add?) changes making up this
+    // mutation. Removals come first so that the intermediate states don't
+    // break weekly hour limits unnecessarily.
+    fn changes(&self) -> SmallVec<[(usize, ApplicantId, bool); 4]> {
+        match *self {
+            Mutation::AddToSession { session, applicant } => {
+                smallvec![(session, applicant, true)]
+            }
+            Mutation::RemoveFromSession { session, applicant } => {
+                smallvec![(session, applicant, false)]
+            }
+            Mutation::Move {
+                from,
+                to,
+                applicant,
+            } => smallvec![(from, applicant, false), (to, applicant, true)],
+            Mutation::Swap {
+                first: (first_session, first_applicant),
+                second: (second_session, second_applicant),
+            } => smallvec![
+                (first_session, first_applicant, false),
+                (second_session, second_applicant, false),
+                (second_session, first_applicant, true),
+                (first_session, second_applicant, true),
+            ],
+            Mutation::Replace {
+                session,
+                old_applicant,
+                new_applicant,
+            } => smallvec![
+                (session, old_applicant, false),
+                (session, new_applicant, true)
+            ],
+        }
+    }
 }
 
 impl<'a> Solver<'a> {
+    fn new(inputs: &SolveInputs<'a>) -> Self {
+        let SolveInputs {
+            applicants,
+            sessions,
+            desired_hours,
+            weights,
+            previous,
+            pinned,
+            conflicts,
+            frozen_weeks,
+        } = *inputs;
+
+        let weeks = desired_hours
+            .iter()
+            .map(|&(course, week_num, desired_total)| {
+                let session_indexes = sessions
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(idx, session)| {
+                        (session.course == course && session.week == week_num).then_some(idx)
+                    })
+                    .collect::<Vec<_>>();
+                Week {
+                    course,
+                    week_num,
+                    desired_total_hours: desired_total,
+                    max_size: session_indexes
+                        .iter()
+                        .map(|&idx| sessions[idx].max_tutors())
+                        .max()
+                        .unwrap_or(0),
+                    session_indexes,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        assert!(
+            weeks.len()
+                == sessions
+                    .iter()
+                    .map(|session| (session.course, session.week))
+                    .collect::<HashSet<_>>()
+                    .len()
+        );
+
+        let session_weeks = sessions
+            .iter()
+            .map(|session| {
+                desired_hours
+                    .iter()
+                    .position(|&(course, week_num, _)| {
+                        course == session.course && week_num == session.week
+                    })
+                    .unwrap_or_else(|| {
+                        panic!("no desired hours for {} {:?}", session.course, session.week)
+                    })
+            })
+            .collect();
+
+        let calendar_weeks = sessions
+            .iter()
+            .map(|session| session.week)
+            .unique()
+            .collect::<Vec<_>>();
+        let session_calendar_weeks = sessions
+            .iter()
+            .map(|session| {
+                calendar_weeks
+                    .iter()
+                    .position(|&week| week == session.week)
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        let calendar_days = sessions
+            .iter()
+            .map(|session| (session.week, session.day))
+            .unique()
+            .collect::<Vec<_>>();
+        let session_calendar_days = sessions
+            .iter()
+            .map(|session| {
+                calendar_days
+                    .iter()
+                    .position(|&day| day == (session.week, session.day))
+                    .unwrap()
+            })
+            .collect();
+        let calendar_week_days = calendar_weeks
+            .iter()
+            .map(|&week_num| {
+                (0..calendar_days.len())
+                    .filter(|&day_index| calendar_days[day_index].0 == week_num)
+                    .collect()
+            })
+            .collect();
+
+        let session_candidates = (0..sessions.len())
+            .map(|session_index| {
+                (0..applicants.len())
+                    .filter(|&idx| {
+                        !matches!(
+                            applicants[idx].availabilities[session_index],
+                            Availability::Impossible
+                        )
+                    })
+                    .map(|idx| idx as ApplicantId)
+                    .collect()
+            })
+            .collect();
+
+        let mut previous_assignments = vec![SmallVec::new(); sessions.len()];
+        // a hand-edited solution can split a session over several blocks
+        for assignment in previous.unwrap_or_default() {
+            let assigned = &mut previous_assignments[assignment.session.id];
+            for previous_applicant in &assignment.applicants {
+                let applicant = applicants
+                    .iter()
+                    .position(|applicant| applicant.zid == previous_applicant.zid)
+                    .unwrap() as ApplicantId;
+                if !assigned.contains(&applicant) {
+                    assigned.push(applicant);
+                }
+            }
+        }
+
+        let previous_occurrences = sessions
+            .iter()
+            .map(|session| {
+                sessions
+                    .iter()
+                    .filter(|other| other.slot == session.slot && other.week < session.week)
+                    .max_by_key(|other| other.week)
+                    .map(|other| other.id)
+            })
+            .collect::<Vec<_>>();
+        let mut next_occurrences = vec![None; sessions.len()];
+        for (session_index, previous) in previous_occurrences.iter().enumerate() {
+            if let Some(previous) = *previous {
+                next_occurrences[previous] = Some(session_index);
+            }
+        }
+
+        let fairness_courses = applicants
+            .iter()
+            .map(|applicant| applicant.course)
+            .unique()
+            .collect::<Vec<_>>();
+        let applicant_fairness_groups = applicants
+            .iter()
+            .map(|applicant| {
+                fairness_courses
+                    .iter()
+                    .position(|&course| course == applicant.course)
+                    .unwrap()
+            })
+            .collect();
+        let fairness_groups = fairness_courses
+            .into_iter()
+            .map(|course| {
+                let requested = applicants
+                    .iter()
+                    .filter(|applicant| applicant.course == course)
+                    .map(|applicant| applicant.requested_hours_per_week as u64);
+                FairnessGroup {
+                    course,
+                    total_requested: requested.clone().sum(),
+                    sum_squared_requested: requested.map(|requested| requested * requested).sum(),
+                }
+            })
+            .collect();
+
+        let mut pinned_assignments = vec![SmallVec::new(); sessions.len()];
+        for pin in pinned {
+            pinned_assignments[pin.session].push(pin.applicant as ApplicantId);
+        }
+
+        let (frozen_sessions, open_sessions): (Vec<usize>, Vec<usize>) = (0..sessions.len())
+            .partition(|&session_index| frozen_weeks.contains(&sessions[session_index].week));
+        for &session_index in &frozen_sessions {
+            for &applicant in &previous_assignments[session_index] {
+                if !pinned_assignments[session_index].contains(&applicant) {
+                    pinned_assignments[session_index].push(applicant);
+                }
+            }
+
+            let session = &sessions[session_index];
+            let num_tutors = pinned_assignments[session_index].len();
+            if num_tutors > session.max_tutors() {
+                panic!(
+                    "frozen {} week {} {} {}:00 ({}) had {num_tutors} tutors, more than its \
+                     maximum of {} (raise it in sessions.txt or priorities.tsv)",
+                    session.course,
+                    session.week.0,
+                    session.day.long_name(),
+                    session.time_24hr,
+                    session.location,
+                    session.max_tutors()
+                );
+            }
+        }
+
+        Solver {
+            sessions,
+            applicants,
+            weights,
+            weeks,
+            session_weeks,
+            calendar_weeks,
+            session_calendar_weeks,
+            calendar_days,
+            calendar_week_days,
+            session_calendar_days,
+            session_conflicts: conflicts,
+            session_candidates,
+            previous_assignments,
+            change_penalty: if previous.is_some() {
+                weights.change_penalty
+            } else {
+                0
+            },
+            previous_occurrences,
+            next_occurrences,
+            fairness_groups,
+            applicant_fairness_groups,
+            pinned: pinned_assignments,
+            open_sessions,
+        }
+    }
+
+    fn preference_cost(&self, availability: Availability) -> Option<Cost> {
+        Some(match availability {
+            Availability::Impossible => return None,
+            Availability::Dislike => self.weights.dislike,
+            Availability::Possible => self.weights.possible,
+            Availability::Preferred => self.weights.preferred,
+        })
+    }
+
+    fn session_cost(&self, session_index: usize, allocation: &SessionAllocation) -> Cost {
+        self.min_allocation_cost(&self.sessions[session_index], allocation.assigned.len())
+            + self.change_cost(session_index, allocation)
+    }
+
+    fn change_cost(&self, session_index: usize, allocation: &SessionAllocation) -> Cost {
+        let assigned = &allocation.assigned;
+        let previous = &self.previous_assignments[session_index];
+        let num_changes = assigned
+            .iter()
+            .filter(|applicant| !previous.contains(applicant))
+            .count()
+            + previous
+                .iter()
+                .filter(|applicant| !assigned.contains(applicant))
+                .count();
+
+        self.change_penalty * (num_changes as Cost)
+    }
+
+    // For the tutors who differ between two consecutive weeks of a slot
+    fn continuity_cost(&self, earlier: &SessionAllocation, later: &SessionAllocation) -> Cost {
+        self.weights.continuity * (earlier.num_differences(later) as Cost)
+    }
+
+    // The continuity costs involving a session, with the weeks either side
+    fn session_continuity_cost(
+        &self,
+        allocations: &[SessionAllocation],
+        session_index: usize,
+    ) -> Cost {
+        if self.weights.continuity == 0 {
+            return 0;
+        }
+
+        let allocation = &allocations[session_index];
+        self.previous_occurrences[session_index].map_or(0, |previous| {
+            self.continuity_cost(&allocations[previous], allocation)
+        }) + self.next_occurrences[session_index].map_or(0, |next| {
+            self.continuity_cost(allocation, &allocations[next])
+        })
+    }
+
+    fn min_allocation_cost(&self, session: &Session, num_tutors: usize) -> Cost {
+        match session.min_allocation {
+            Some(min_allocation) if num_tutors < min_allocation as usize => {
+                self.weights.min_allocation_shortfall
+                    * ((min_allocation as usize - num_tutors) as Cost)
+            }
+            _ => 0,
+        }
+    }
+
+    fn min_hours_cost(&self, applicant: &Applicant, weekly_total: HourCount) -> Cost {
+        match applicant.min_hours_per_week {
+            Some(min_hours) if weekly_total < min_hours => self
+                .weights
+                .min_hours_shortfall_cost(min_hours - weekly_total),
+            _ => 0,
+        }
+    }
+
+    fn weekly_hours_cost(&self, week: &Week, effective_hours: HourCount) -> Cost {
+        if effective_hours < week.desired_total_hours {
+            self.weights
+                .under_target_cost(week.desired_total_hours - effective_hours)
+        } else {
+            self.weights.over_target * ((effective_hours - week.desired_total_hours) as Cost)
+        }
+    }
+
+    // `daily_sessions` is the number of sessions the applicant has on each day
+    // of a week
+    fn day_preference_cost(
+        &self,
+        applicant: &Applicant,
+        daily_sessions: impl Iterator<Item = u16>,
+    ) -> Cost {
+        let extra = match applicant.day_preference {
+            Some(DayPreference::Cluster) => daily_sessions
+                .filter(|&sessions| sessions > 0)
+                .count()
+                .saturating_sub(1),
+            Some(DayPreference::Spread) => daily_sessions
+                .map(|sessions| sessions.saturating_sub(1) as usize)
+                .sum(),
+            None => 0,
+        };
+        self.weights.day_preference * (extra as Cost)
+    }
+
+    fn size_imbalance_cost(&self, min_size: usize, max_size: usize) -> Cost {
+        if max_size > min_size + self.weights.size_imbalance_tolerance {
+            self.weights.size_imbalance * ((max_size - min_size) as Cost)
+        } else {
+            0
+        }
+    }
+
+    fn term_minimum_cost(&self, applicant: &Applicant, overall_hours: HourCount) -> Cost {
+        let (term_minimum, shortfall_cost) = self.weights.term_minimum(applicant);
+        if overall_hours > term_minimum {
+            0
+        } else {
+            ((term_minimum - overall_hours) as Cost) * shortfall_cost
+        }
+    }
+
+    // weight * sum((h - H r / R)^2) over the group's tutors, where h is a
+    // tutor's hours, r the hours they requested, and H and R the group's
+    // totals of each. Multiplying out the square means only the sums are
+    // needed.
+    fn fairness_cost(&self, group: &FairnessGroup, sums: &FairnessSums) -> Cost {
+        if self.weights.fairness == 0 || group.total_requested == 0 {
+            return 0;
+        }
+
+        let total_requested = group.total_requested as i128;
+        let hours = sums.hours as i128;
+        let scaled_squared_deviation =
+            total_requested * total_requested * (sums.squared_hours as i128)
+                - 2 * total_requested * hours * (sums.hours_times_requested as i128)
+                + hours * hours * (group.sum_squared_requested as i128);
+
+        ((self.weights.fairness as i128) * scaled_squared_deviation
+            / (total_requested * total_requested)) as Cost
+    }
+
     fn eval_allocation(&self, allocations: &[SessionAllocation]) -> Option<Cost> {
         let mut total_cost: Cost = 0;
+        let mut feasible = true;
+        self.eval_allocation_terms(
+            allocations,
+            |item| total_cost += item.cost,
+            |_| feasible = false,
+        );
+        feasible.then_some(total_cost)
+    }
 
+    // Passes each cost term to `record` separately, and anything making the
+    // allocation infeasible to `violated`.
+    fn eval_allocation_terms(
+        &self,
+        allocations: &[SessionAllocation],
+        mut record: impl FnMut(CostItem),
+        mut violated: impl FnMut(Violation),
+    ) {
         let mut applicant_overall_total: Vec<HourCount> = [0].repeat(self.applicants.len());
+        let mut applicant_weekly_totals: Vec<Vec<HourCount>> =
+            vec![vec![0; self.applicants.len()]; self.calendar_weeks.len()];
+        let mut applicant_daily_totals: Vec<Vec<HourCount>> =
+            vec![vec![0; self.applicants.len()]; self.calendar_days.len()];
+        let mut applicant_daily_sessions: Vec<Vec<u16>> =
+            vec![vec![0; self.applicants.len()]; self.calendar_days.len()];
 
         for week in &self.weeks {
             let mut effective_hours_this_week = 0;
-            let mut applicant_weekly_total: Vec<HourCount> = [0].repeat(self.applicants.len());
 
-            let mut min_size_this_week = MAX_TUTORS_PER_SESSION;
+            let mut min_size_this_week = week.max_size;
             let mut max_size_this_week = 0;
 
+            let week_item = |term, session, applicant, cost| CostItem {
+                term,
+                course: week.course,
+                week: Some(week.week_num),
+                session,
+                applicant,
+                cost,
+            };
+
             for session_index in week.session_indexes.iter().copied() {
                 let allocation = &allocations[session_index];
                 let session = &self.sessions[session_index];
@@ -66,272 +784,1298 @@ impl<'a> Solver<'a> {
                 for applicant_index in allocation.assigned.iter().copied() {
                     let availability =
                         self.applicants[applicant_index as usize].availabilities[session_index];
-                    total_cost += match availability {
-                        Availability::Impossible => return None,
-                        Availability::Dislike => 100,
-                        Availability::Possible => 5,
-                        Availability::Preferred => 0,
-                    };
-
-                    applicant_weekly_total[applicant_index as usize] += session_length;
+                    match self.preference_cost(availability) {
+                        Some(cost) => record(week_item(
+                            CostTerm::Preference,
+                            Some(session_index),
+                            Some(applicant_index as usize),
+                            cost,
+                        )),
+                        None => violated(Violation::Impossible {
+                            session: session_index,
+                            applicant: applicant_index as usize,
+                        }),
+                    }
+
+                    // each clashing pair is only reported from its first session
+                    for &other in &self.session_conflicts[session_index] {
+                        if other > session_index
+                            && allocations[other].assigned.contains(&applicant_index)
+                        {
+                            violated(Violation::Clash {
+                                session: session_index,
+                                other,
+                                applicant: applicant_index as usize,
+                            });
+                        }
+                    }
+
+                    let calendar_day_index = self.session_calendar_days[session_index];
+                    applicant_weekly_totals[self.session_calendar_weeks[session_index]]
+                        [applicant_index as usize] += session_length;
+                    applicant_daily_totals[calendar_day_index][applicant_index as usize] +=
+                        session_length;
+                    applicant_daily_sessions[calendar_day_index][applicant_index as usize] += 1;
                     applicant_overall_total[applicant_index as usize] += session_length;
                 }
 
                 let num_tutors = allocation.assigned.len();
+                if num_tutors > session.max_tutors() {
+                    violated(Violation::Overfull {
+                        session: session_index,
+                        num_tutors,
+                        max_tutors: session.max_tutors(),
+                    });
+                }
                 if num_tutors > 0 && session.min_allocation.is_none() {
                     min_size_this_week = min(min_size_this_week, num_tutors);
                     max_size_this_week = max(max_size_this_week, num_tutors);
                 }
 
-                if let Some(min_allocation) = session.min_allocation {
-                    let min_allocation = min_allocation as usize;
-                    if num_tutors < min_allocation {
-                        total_cost += 50 * ((min_allocation - num_tutors) as Cost);
-                    }
+                record(week_item(
+                    CostTerm::MinAllocationShortfall,
+                    Some(session_index),
+                    None,
+                    self.min_allocation_cost(session, num_tutors),
+                ));
+                record(week_item(
+                    CostTerm::Change,
+                    Some(session_index),
+                    None,
+                    self.change_cost(session_index, allocation),
+                ));
+                if let Some(previous) = self.previous_occurrences[session_index] {
+                    record(week_item(
+                        CostTerm::Continuity,
+                        Some(session_index),
+                        None,
+                        self.continuity_cost(&allocations[previous], allocation),
+                    ));
                 }
             }
 
-            for (applicant_total, applicant) in applicant_weekly_total.iter().zip(self.applicants) {
+            let target_term = if effective_hours_this_week < week.desired_total_hours {
+                CostTerm::UnderTarget
+            } else {
+                CostTerm::OverTarget
+            };
+            record(week_item(
+                target_term,
+                None,
+                None,
+                self.weekly_hours_cost(week, effective_hours_this_week),
+            ));
+            record(week_item(
+                CostTerm::SizeImbalance,
+                None,
+                None,
+                self.size_imbalance_cost(min_size_this_week, max_size_this_week),
+            ));
+        }
+
+        for (calendar_week_index, (applicant_weekly_total, &week_num)) in applicant_weekly_totals
+            .iter()
+            .zip(&self.calendar_weeks)
+            .enumerate()
+        {
+            let days = &self.calendar_week_days[calendar_week_index];
+            for (applicant_index, (applicant_total, applicant)) in applicant_weekly_total
+                .iter()
+                .zip(self.applicants)
+                .enumerate()
+            {
                 if *applicant_total > applicant.max_hours_per_week {
-                    return None;
+                    violated(Violation::WeeklyCap {
+                        applicant: applicant_index,
+                        week: week_num,
+                        hours: *applicant_total,
+                        max_hours: applicant.max_hours_per_week,
+                    });
                 }
-
-                if let Some(min_hours) = applicant.min_hours_per_week {
-                    if *applicant_total < min_hours {
-                        total_cost +=
-                            20 * (((min_hours - *applicant_total) as f32).powf(1.5) as Cost);
+                if let Some(max_hours) = applicant.max_hours_per_day {
+                    for &day in days {
+                        let hours = applicant_daily_totals[day][applicant_index];
+                        if hours > max_hours {
+                            violated(Violation::DailyCap {
+                                applicant: applicant_index,
+                                week: week_num,
+                                day: self.calendar_days[day].1,
+                                hours,
+                                max_hours,
+                            });
+                        }
                     }
                 }
+
+                record(CostItem {
+                    term: CostTerm::DayPreference,
+                    course: applicant.course,
+                    week: Some(week_num),
+                    session: None,
+                    applicant: Some(applicant_index),
+                    cost: self.day_preference_cost(
+                        applicant,
+                        days.iter()
+                            .map(|&day| applicant_daily_sessions[day][applicant_index]),
+                    ),
+                });
+
+                record(CostItem {
+                    term: CostTerm::MinHoursShortfall,
+                    course: applicant.course,
+                    week: Some(week_num),
+                    session: None,
+                    applicant: Some(applicant_index),
+                    cost: self.min_hours_cost(applicant, *applicant_total),
+                });
             }
+        }
 
-            if effective_hours_this_week < week.desired_total_hours {
-                total_cost +=
-                    20 * ((week.desired_total_hours - effective_hours_this_week) as Cost).pow(2);
-            } else {
-                let diff = (effective_hours_this_week - week.desired_total_hours) as Cost;
-                total_cost += 200 * diff;
+        for (applicant_index, &hours) in applicant_overall_total.iter().enumerate() {
+            let applicant = &self.applicants[applicant_index];
+            if let Some(max_hours) = applicant.max_hours_per_term {
+                if hours > max_hours {
+                    violated(Violation::TermCap {
+                        applicant: applicant_index,
+                        hours,
+                        max_hours,
+                    });
+                }
             }
 
-            if max_size_this_week > min_size_this_week + 2 {
-                total_cost += 50 * ((max_size_this_week - min_size_this_week) as Cost);
+            record(CostItem {
+                term: CostTerm::TermMinimumShortfall,
+                course: applicant.course,
+                week: None,
+                session: None,
+                applicant: Some(applicant_index),
+                cost: self.term_minimum_cost(applicant, hours),
+            });
+        }
+
+        let mut fairness_sums = vec![FairnessSums::default(); self.fairness_groups.len()];
+        for (applicant_index, &hours) in applicant_overall_total.iter().enumerate() {
+            fairness_sums[self.applicant_fairness_groups[applicant_index]].add_tutor(
+                hours,
+                self.applicants[applicant_index].requested_hours_per_week,
+            );
+        }
+        for (group, sums) in self.fairness_groups.iter().zip(&fairness_sums) {
+            record(CostItem {
+                term: CostTerm::Fairness,
+                course: group.course,
+                week: None,
+                session: None,
+                applicant: None,
+                cost: self.fairness_cost(group, sums),
+            });
+        }
+    }
+
+    // A hand-edited solution can split a session over several blocks, so
+    // they're merged, and any tutor listed twice in a session is `violated`
+    fn allocations_from_solution(
+        &self,
+        solution: &[SolvedSession],
+        mut violated: impl FnMut(Violation),
+    ) -> Vec<SessionAllocation> {
+        let mut allocations = vec![
+            SessionAllocation {
+                assigned: Default::default(),
+            };
+            self.sessions.len()
+        ];
+        for assignment in solution {
+            let session_index = assignment.session.id;
+            for solved_applicant in &assignment.applicants {
+                let applicant_index = self
+                    .applicants
+                    .iter()
+                    .position(|applicant| applicant.zid == solved_applicant.zid)
+                    .unwrap_or_else(|| panic!("{} isn't an applicant", solved_applicant.zid));
+                let assigned = &mut allocations[session_index].assigned;
+                if assigned.contains(&(applicant_index as ApplicantId)) {
+                    violated(Violation::Duplicate {
+                        session: session_index,
+                        applicant: applicant_index,
+                    });
+                } else {
+                    assigned.push(applicant_index as ApplicantId);
+                }
             }
         }
+        allocations
+    }
 
-        total_cost += applicant_overall_total
-            .into_iter()
-            .map(|hours| (if hours > 6 { 0 } else { (6 - hours) * 10 }) as Cost)
-            .sum::<Cost>();
+    fn empty_state(&self) -> AllocationState {
+        let allocations = (0..self.sessions.len())
+            .map(|_| SessionAllocation {
+                assigned: Default::default(),
+            })
+            .collect::<Vec<_>>();
+
+        AllocationState {
+            cost: self.eval_allocation(&allocations).unwrap(),
+            allocations,
+            applicant_weekly_totals: vec![
+                vec![0; self.applicants.len()];
+                self.calendar_weeks.len()
+            ],
+            applicant_daily_totals: vec![vec![0; self.applicants.len()]; self.calendar_days.len()],
+            applicant_daily_sessions: vec![
+                vec![0; self.applicants.len()];
+                self.calendar_days.len()
+            ],
+            applicant_overall_totals: vec![0; self.applicants.len()],
+            weekly_effective_hours: vec![0; self.weeks.len()],
+            weekly_size_counts: self
+                .weeks
+                .iter()
+                .map(|week| vec![0; week.max_size + 1])
+                .collect(),
+            fairness_sums: vec![FairnessSums::default(); self.fairness_groups.len()],
+        }
+    }
 
-        // TOOD: disincentive not giving many hours to tutors who requested many
+    // Starts from the pinned assignments, plus the previous solution if there
+    // is one, leaving out anything which is no longer feasible.
+    fn initial_state(&self) -> AllocationState {
+        let mut state = self.empty_state();
+        for (session_index, pinned) in self.pinned.iter().enumerate() {
+            for &applicant in pinned {
+                if self
+                    .change_assignment(&mut state, session_index, applicant, true)
+                    .is_none()
+                {
+                    let session = &self.sessions[session_index];
+                    panic!(
+                        "{} is pinned to week {} {} {}:00 but that takes them over their hours",
+                        self.applicants[applicant as usize].zid,
+                        session.week.0,
+                        session.day.long_name(),
+                        session.time_24hr
+                    );
+                }
+            }
+        }
 
-        Some(total_cost)
+        for (session_index, previous) in self.previous_assignments.iter().enumerate() {
+            for &applicant in previous {
+                if self.pinned[session_index].contains(&applicant) {
+                    continue;
+                }
+                let feasible = state.allocations[session_index].assigned.len()
+                    < self.sessions[session_index].max_tutors()
+                    && self
+                        .change_assignment(&mut state, session_index, applicant, true)
+                        .is_some();
+                if !feasible {
+                    let session = &self.sessions[session_index];
+                    println!(
+                        "warning: can't keep {} in week {} {} {}:00, starting without them",
+                        self.applicants[applicant as usize].zid,
+                        session.week.0,
+                        session.day.long_name(),
+                        session.time_24hr
+                    );
+                }
+            }
+        }
+        state
     }
 
-    fn mutate_allocation(&self, allocations: &mut [SessionAllocation]) -> Option<Mutation> {
-        let session_index = fastrand::usize(..allocations.len());
+    fn state_from_allocations(&self, allocations: &[SessionAllocation]) -> Option<AllocationState> {
+        let mut state = self.empty_state();
+        for (session_index, allocation) in allocations.iter().enumerate() {
+            for &applicant in &allocation.assigned {
+                self.change_assignment(&mut state, session_index, applicant, true)?;
+            }
+        }
+        Some(state)
+    }
 
-        let action = fastrand::u8(0..=1);
+    // The sum of every cost term which depends on the totals touched by
+    // adding or removing `applicant` from `session_index`.
+    fn local_cost(&self, state: &AllocationState, session_index: usize, applicant: usize) -> Cost {
+        let week_index = self.session_weeks[session_index];
+        let fairness_group = self.applicant_fairness_groups[applicant];
+        let week = &self.weeks[week_index];
 
-        let assigned = &mut allocations[session_index].assigned;
+        let size_counts = &state.weekly_size_counts[week_index];
+        let mut sizes = (1..size_counts.len()).filter(|&size| size_counts[size] > 0);
+        let imbalance_cost = match sizes.next() {
+            Some(min_size) => {
+                self.size_imbalance_cost(min_size, sizes.next_back().unwrap_or(min_size))
+            }
+            None => 0,
+        };
 
-        match action {
-            0 => {
-                if assigned.len() == MAX_TUTORS_PER_SESSION {
-                    // full!
-                    return None;
-                }
+        let calendar_week_index = self.session_calendar_weeks[session_index];
+        let day_preference_cost = self.day_preference_cost(
+            &self.applicants[applicant],
+            self.calendar_week_days[calendar_week_index]
+                .iter()
+                .map(|&day| state.applicant_daily_sessions[day][applicant]),
+        );
 
-                // add a random applicant
-                let all_possible_applicants = self
-                    .applicants
-                    .iter()
-                    .enumerate()
-                    .filter_map(|(idx, applicant)| {
-                        (!matches!(
-                            applicant.availabilities[session_index],
-                            Availability::Impossible
-                        ) && !assigned.contains(&(idx as _)))
-                        .then_some(idx)
+        self.session_cost(session_index, &state.allocations[session_index])
+            + self.session_continuity_cost(&state.allocations, session_index)
+            + day_preference_cost
+            + self.min_hours_cost(
+                &self.applicants[applicant],
+                state.applicant_weekly_totals[calendar_week_index][applicant],
+            )
+            + self.weekly_hours_cost(week, state.weekly_effective_hours[week_index])
+            + imbalance_cost
+            + self.term_minimum_cost(
+                &self.applicants[applicant],
+                state.applicant_overall_totals[applicant],
+            )
+            + self.fairness_cost(
+                &self.fairness_groups[fairness_group],
+                &state.fairness_sums[fairness_group],
+            )
+    }
+
+    // Adds or removes a single assignment, keeping the running totals up to
+    // date. Returns the change in cost, or `None` (leaving the state untouched)
+    // if the result would be infeasible.
+    fn change_assignment(
+        &self,
+        state: &mut AllocationState,
+        session_index: usize,
+        applicant: ApplicantId,
+        add: bool,
+    ) -> Option<i64> {
+        let session = &self.sessions[session_index];
+        let week_index = self.session_weeks[session_index];
+        let calendar_week_index = self.session_calendar_weeks[session_index];
+        let calendar_day_index = self.session_calendar_days[session_index];
+        let applicant_index = applicant as usize;
+        let length = session.length_hours as HourCount;
+
+        let preference =
+            self.preference_cost(self.applicants[applicant_index].availabilities[session_index]);
+        if add
+            && (preference.is_none()
+                || state.applicant_weekly_totals[calendar_week_index][applicant_index] + length
+                    > self.applicants[applicant_index].max_hours_per_week
+                || self.applicants[applicant_index]
+                    .max_hours_per_day
+                    .is_some_and(|max_hours| {
+                        state.applicant_daily_totals[calendar_day_index][applicant_index] + length
+                            > max_hours
                     })
-                    .collect::<Vec<_>>();
+                || self.applicants[applicant_index]
+                    .max_hours_per_term
+                    .is_some_and(|max_hours| {
+                        state.applicant_overall_totals[applicant_index] + length > max_hours
+                    })
+                || self.session_conflicts[session_index]
+                    .iter()
+                    .any(|&other| state.allocations[other].assigned.contains(&applicant)))
+        {
+            return None;
+        }
 
-                if all_possible_applicants.is_empty() {
-                    // no possible applicants
-                    return None;
-                }
+        let cost_before = self.local_cost(state, session_index, applicant_index);
 
-                let applicant =
-                    all_possible_applicants[fastrand::usize(0..all_possible_applicants.len())] as _;
+        let requested = self.applicants[applicant_index].requested_hours_per_week;
+        let fairness_sums =
+            &mut state.fairness_sums[self.applicant_fairness_groups[applicant_index]];
+        fairness_sums.remove_tutor(state.applicant_overall_totals[applicant_index], requested);
 
-                assigned.push(applicant);
+        let assigned = &mut state.allocations[session_index].assigned;
+        let old_size = assigned.len();
+        if add {
+            assigned.push(applicant);
+            state.applicant_weekly_totals[calendar_week_index][applicant_index] += length;
+            state.applicant_daily_totals[calendar_day_index][applicant_index] += length;
+            state.applicant_daily_sessions[calendar_day_index][applicant_index] += 1;
+            state.applicant_overall_totals[applicant_index] += length;
+            state.weekly_effective_hours[week_index] += length;
+        } else {
+            let position = assigned.iter().position(|&a| a == applicant).unwrap();
+            assigned.remove(position);
+            state.applicant_weekly_totals[calendar_week_index][applicant_index] -= length;
+            state.applicant_daily_totals[calendar_day_index][applicant_index] -= length;
+            state.applicant_daily_sessions[calendar_day_index][applicant_index] -= 1;
+            state.applicant_overall_totals[applicant_index] -= length;
+            state.weekly_effective_hours[week_index] -= length;
+        }
+        let new_size = assigned.len();
 
-                Some(Mutation::AddToSession {
-                    session: session_index,
-                    applicant,
-                })
+        fairness_sums.add_tutor(state.applicant_overall_totals[applicant_index], requested);
+
+        if session.min_allocation.is_none() {
+            let size_counts = &mut state.weekly_size_counts[week_index];
+            if old_size > 0 {
+                size_counts[old_size] -= 1;
+            }
+            if new_size > 0 {
+                size_counts[new_size] += 1;
             }
-            1 => {
-                // remove a random applicant
-                if assigned.is_empty() {
+        }
+
+        let cost_after = self.local_cost(state, session_index, applicant_index);
+
+        let preference_change = preference.unwrap_or(0) as i64;
+        let cost_change = (cost_after as i64) - (cost_before as i64)
+            + if add {
+                preference_change
+            } else {
+                -preference_change
+            };
+
+        state.cost = ((state.cost as i64) + cost_change) as Cost;
+        Some(cost_change)
+    }
+
+    fn apply_mutation(&self, state: &mut AllocationState, mutation: &Mutation) -> Option<i64> {
+        let changes = mutation.changes();
+        let mut total_change = 0;
+
+        for (done, &(session, applicant, add)) in changes.iter().enumerate() {
+            match self.change_assignment(state, session, applicant, add) {
+                Some(cost_change) => total_change += cost_change,
+                None => {
+                    for &(session, applicant, add) in changes[..done].iter().rev() {
+                        self.change_assignment(state, session, applicant, !add)
+                            .expect("undoing a change should always be feasible");
+                    }
                     return None;
                 }
+            }
+        }
+
+        Some(total_change)
+    }
+
+    fn undo_mutation(&self, state: &mut AllocationState, mutation: &Mutation) {
+        for (session, applicant, add) in mutation.changes().into_iter().rev() {
+            self.change_assignment(state, session, applicant, !add)
+                .expect("undoing a mutation should always be feasible");
+        }
+    }
+
+    // Proposes a mutation and accepts or rejects it. Returns whether the state
+    // changed.
+    fn anneal_step(
+        &self,
+        rng: &mut Rng,
+        state: &mut AllocationState,
+        options: &SolveOptions,
+        temperature: impl FnOnce() -> f32,
+    ) -> bool {
+        let Some(mutation) = self.propose_mutation(rng, &state.allocations, options) else {
+            return false;
+        };
+        let Some(cost_change) = self.apply_mutation(state, &mutation) else {
+            return false;
+        };
+
+        let accepted = if cost_change <= 0 {
+            true
+        } else {
+            // possibly allow a bump, depending on temp
+            let cost_increase = cost_change as f32;
+            let accept_prob = (-cost_increase / temperature()).exp();
+            rng.f32() < accept_prob
+        };
+
+        if !accepted {
+            self.undo_mutation(state, &mutation);
+        }
+        // else {
+        //     println!(
+        //         "improved by {} with {:?}, current cost: {}",
+        //         -cost_change, mutation, state.cost
+        //     );
+        // }
+
+        if options.check_costs {
+            assert_eq!(
+                Some(state.cost),
+                self.eval_allocation(&state.allocations),
+                "running cost disagrees with full evaluation after {mutation:?} (accepted: {accepted})"
+            );
+        }
+
+        accepted
+    }
+
+    fn random_unassigned_candidate(
+        &self,
+        rng: &mut Rng,
+        session_index: usize,
+        assigned: &[ApplicantId],
+    ) -> Option<ApplicantId> {
+        let all_possible_applicants = self.session_candidates[session_index]
+            .iter()
+            .filter(|applicant| !assigned.contains(applicant))
+            .collect::<SmallVec<[_; 64]>>();
+
+        if all_possible_applicants.is_empty() {
+            return None;
+        }
+
+        Some(*all_possible_applicants[rng.usize(0..all_possible_applicants.len())])
+    }
+
+    fn random_unpinned_assigned(
+        &self,
+        rng: &mut Rng,
+        session_index: usize,
+        assigned: &[ApplicantId],
+    ) -> Option<ApplicantId> {
+        let unpinned = assigned
+            .iter()
+            .filter(|applicant| !self.pinned[session_index].contains(applicant))
+            .collect::<SmallVec<[_; DEFAULT_MAX_ALLOCATION]>>();
 
-                let applicant_index = fastrand::usize(0..assigned.len());
-                let applicant = assigned[applicant_index];
-                assigned.remove(applicant_index);
+        if unpinned.is_empty() {
+            return None;
+        }
+
+        Some(*unpinned[rng.usize(0..unpinned.len())])
+    }
 
-                Some(Mutation::RemoveFromSession {
-                    session: session_index,
-                    applicant,
+    fn propose_mutation(
+        &self,
+        rng: &mut Rng,
+        allocations: &[SessionAllocation],
+        options: &SolveOptions,
+    ) -> Option<Mutation> {
+        if self.open_sessions.is_empty() {
+            return None;
+        }
+        let session_index = self.open_sessions[rng.usize(..self.open_sessions.len())];
+
+        let assigned = &allocations[session_index].assigned;
+
+        let roll = rng.f32();
+        let move_threshold = options.move_probability;
+        let swap_threshold = move_threshold + options.swap_probability;
+        let replace_threshold = swap_threshold + options.replace_probability;
+
+        if roll < move_threshold {
+            // move a random applicant to another session in the same week
+            let applicant = self.random_unpinned_assigned(rng, session_index, assigned)?;
+
+            let week = &self.weeks[self.session_weeks[session_index]];
+            let targets = week
+                .session_indexes
+                .iter()
+                .copied()
+                .filter(|&target| {
+                    let target_assigned = &allocations[target].assigned;
+                    target_assigned.len() < self.sessions[target].max_tutors()
+                        && !target_assigned.contains(&applicant)
+                        && self.session_candidates[target].contains(&applicant)
                 })
+                .collect::<SmallVec<[_; 32]>>();
+
+            if targets.is_empty() {
+                return None;
+            }
+
+            Some(Mutation::Move {
+                from: session_index,
+                to: targets[rng.usize(0..targets.len())],
+                applicant,
+            })
+        } else if roll < swap_threshold {
+            // swap applicants between two sessions in the same week
+            let week = &self.weeks[self.session_weeks[session_index]];
+            let other_session = week.session_indexes[rng.usize(0..week.session_indexes.len())];
+            let other_assigned = &allocations[other_session].assigned;
+
+            if other_session == session_index || assigned.is_empty() || other_assigned.is_empty() {
+                return None;
+            }
+
+            let applicant = self.random_unpinned_assigned(rng, session_index, assigned)?;
+            let other_applicant =
+                self.random_unpinned_assigned(rng, other_session, other_assigned)?;
+
+            if assigned.contains(&other_applicant)
+                || other_assigned.contains(&applicant)
+                || !self.session_candidates[other_session].contains(&applicant)
+                || !self.session_candidates[session_index].contains(&other_applicant)
+            {
+                return None;
             }
-            _ => panic!(),
+
+            Some(Mutation::Swap {
+                first: (session_index, applicant),
+                second: (other_session, other_applicant),
+            })
+        } else if roll < replace_threshold {
+            // replace a random applicant with someone not already there
+            let old_applicant = self.random_unpinned_assigned(rng, session_index, assigned)?;
+            let new_applicant = self.random_unassigned_candidate(rng, session_index, assigned)?;
+
+            Some(Mutation::Replace {
+                session: session_index,
+                old_applicant,
+                new_applicant,
+            })
+        } else if rng.bool() {
+            if assigned.len() >= self.sessions[session_index].max_tutors() {
+                // full!
+                return None;
+            }
+
+            // add a random applicant
+            let applicant = self.random_unassigned_candidate(rng, session_index, assigned)?;
+
+            Some(Mutation::AddToSession {
+                session: session_index,
+                applicant,
+            })
+        } else {
+            // remove a random applicant
+            let applicant = self.random_unpinned_assigned(rng, session_index, assigned)?;
+
+            Some(Mutation::RemoveFromSession {
+                session: session_index,
+                applicant,
+            })
         }
     }
 }
 
 fn solve(
-    applicants: &[Applicant],
+    inputs: &SolveInputs,
+    options: &SolveOptions,
+    seed: Seed,
+) -> (Cost, Vec<SessionAllocation>, u64) {
+    let mut rng = Rng::with_seed(seed);
+    let solver = Solver::new(inputs);
+
+    let mut state = solver.initial_state();
+    // println!("initial cost: {}", state.cost);
+
+    let start = Instant::now();
+    let mut best_cost = state.cost;
+    let mut best_allocations = state.allocations.clone();
+
+    let cycles = (options.reheats + 1) as f32;
+    let mut cycle = 0;
+
+    let mut step = 0;
+    let mut steps_since_improvement = 0;
+    let mut time_progress = 0.0;
+
+    loop {
+        // checking the time on every step is surprisingly slow
+        if step % 1024 == 0 {
+            time_progress = options.time_progress(start);
+        }
+
+        if options.budget_exhausted(time_progress, step, steps_since_improvement) {
+            break;
+        }
+
+        step += 1;
+        steps_since_improvement += 1;
+
+        let overall_progress = time_progress.max(options.step_progress(step)) * cycles;
+        let this_cycle = (overall_progress as u32).min(options.reheats);
+        if this_cycle != cycle {
+            cycle = this_cycle;
+            if options.restart_from_best {
+                state = solver
+                    .state_from_allocations(&best_allocations)
+                    .expect("best allocation should be feasible");
+            }
+        }
+        let progress = overall_progress - (cycle as f32);
+
+        let accepted = solver.anneal_step(&mut rng, &mut state, options, || {
+            options.cooling.temperature(progress)
+        });
+
+        if accepted && state.cost < best_cost {
+            best_cost = state.cost;
+            best_allocations = state.allocations.clone();
+            steps_since_improvement = 0;
+        }
+    }
+
+    (best_cost, best_allocations, step)
+}
+
+fn to_solved_sessions(
+    allocations: Vec<SessionAllocation>,
     sessions: &[Session],
-    desired_hours: &[(WeekNum, HourCount)],
-    quick: bool,
-) -> (Cost, Vec<SessionAllocation>) {
-    let weeks = desired_hours
-        .iter()
-        .map(|(week_num, desired_total)| Week {
-            desired_total_hours: *desired_total,
-            session_indexes: sessions
-                .iter()
-                .enumerate()
-                .filter_map(|(idx, session)| (session.week == *week_num).then_some(idx))
+    applicants: &[Applicant],
+) -> Vec<SolvedSession> {
+    allocations
+        .into_iter()
+        .enumerate()
+        .map(|(session_index, allocation)| SolvedSession {
+            session: sessions[session_index].clone(),
+            applicants: allocation
+                .assigned
+                .into_iter()
+                .map(|applicant_index| applicants[applicant_index as usize].clone())
                 .collect(),
         })
+        .collect()
+}
+
+pub fn solve_many_times(
+    seeds: Vec<Seed>,
+    courses: &[Course],
+    inputs: &SolveInputs,
+    options: &SolveOptions,
+) -> (Vec<SolvedSession>, Seed, Cost, Vec<Alternative>) {
+    if seeds.is_empty() {
+        panic!("no seeds!");
+    }
+
+    // With a deadline, seeds queued behind busy threads would only start once
+    // it had passed, so they're run in waves which split the time between them
+    let wave_size = match options.deadline {
+        Some(_) => rayon::current_num_threads(),
+        None => seeds.len(),
+    };
+    let num_waves = seeds.len().div_ceil(wave_size) as u32;
+    let start = Instant::now();
+
+    let results = seeds
+        .chunks(wave_size)
+        .zip(1..)
+        .flat_map(|(wave_seeds, wave)| {
+            let wave_options = SolveOptions {
+                deadline: options.deadline.map(|deadline| {
+                    start + deadline.saturating_duration_since(start) * wave / num_waves
+                }),
+                ..options.clone()
+            };
+            wave_seeds
+                .par_iter()
+                .map(|&seed| {
+                    let (cost, solution, steps) = solve(inputs, &wave_options, seed);
+                    if seeds.len() > 1 {
+                        println!("seed = {seed}, cost = {cost} after {steps} steps");
+                    }
+                    (seed, cost, solution, steps)
+                })
+                .collect::<Vec<_>>()
+        })
+        .filter_map(|(seed, cost, solution, steps)| {
+            if steps == 0 {
+                println!("warning: seed {seed} ran out of time before taking a step, ignoring it");
+                return None;
+            }
+            Some((seed, cost, solution))
+        })
         .collect::<Vec<_>>();
 
+    let (best_seed, best_cost, solution, alternatives) =
+        pick_alternatives(inputs, results, options);
+
+    println!("best_cost = {best_cost:?} with seed {best_seed:?} (for {courses:?})");
+    // println!("solution = {solution:?}");
+
+    (solution, best_seed, best_cost, alternatives)
+}
+
+// Takes the cheapest of each seed's results, then as many of the next
+// cheapest as `options` asks for which are far enough from the best and from
+// each other.
+fn pick_alternatives(
+    inputs: &SolveInputs,
+    mut results: Vec<(Seed, Cost, Vec<SessionAllocation>)>,
+    options: &SolveOptions,
+) -> (Seed, Cost, Vec<SolvedSession>, Vec<Alternative>) {
+    results.sort_by_key(|&(_, cost, _)| cost);
+    let mut results = results.into_iter();
+    let (best_seed, best_cost, best_allocations) = results
+        .next()
+        .expect("no seeds took any steps (try a longer --time-limit)");
+
+    let mut picked: Vec<(Seed, Cost, Vec<SessionAllocation>)> = vec![];
+    for (seed, cost, allocations) in results {
+        if picked.len() == options.alternatives {
+            break;
+        }
+        let far_enough = std::iter::once(&best_allocations)
+            .chain(picked.iter().map(|(_, _, other)| other))
+            .all(|other| assignment_distance(&allocations, other) >= options.alternative_distance);
+        if far_enough {
+            picked.push((seed, cost, allocations));
+        }
+    }
+
+    if picked.len() < options.alternatives {
+        println!(
+            "only found {} of {} alternatives at least {} assignments apart (try more seeds)",
+            picked.len(),
+            options.alternatives,
+            options.alternative_distance
+        );
+    }
+
+    let alternatives = picked
+        .into_iter()
+        .map(|(seed, cost, allocations)| Alternative {
+            distance: assignment_distance(&allocations, &best_allocations),
+            solution: to_solved_sessions(allocations, inputs.sessions, inputs.applicants),
+            seed,
+            cost,
+        })
+        .collect();
+
+    (
+        best_seed,
+        best_cost,
+        to_solved_sessions(best_allocations, inputs.sessions, inputs.applicants),
+        alternatives,
+    )
+}
+
+// Mixed into the first seed for the exchange RNG, so that it doesn't repeat the
+// first replica's stream (any well mixed constant would do)
+const EXCHANGE_SEED_MIX: Seed = 0x9e37_79b9_7f4a_7c15;
+
+struct Replica {
+    seed: Seed,
+    rng: Rng,
+    state: AllocationState,
+    best_cost: Cost,
+    best_allocations: Vec<SessionAllocation>,
+}
+
+// Parallel tempering: one replica per seed, each held at a fixed temperature
+// on a geometric ladder, with neighbouring temperatures periodically trading
+// states so that good states can escape local minima at the hotter end and
+// be refined at the colder end.
+pub fn solve_with_tempering(
+    seeds: Vec<Seed>,
+    courses: &[Course],
+    inputs: &SolveInputs,
+    options: &SolveOptions,
+    tempering: &TemperingOptions,
+) -> (Vec<SolvedSession>, Seed, Cost, Vec<Alternative>) {
     assert!(
-        weeks.len()
-            == sessions
-                .iter()
-                .map(|session| session.week)
-                .collect::<HashSet<_>>()
-                .len()
+        seeds.len() >= 2,
+        "parallel tempering needs at least two seeds"
     );
 
-    let solver = Solver {
-        sessions,
-        applicants,
-        weeks,
-    };
+    let solver = Solver::new(inputs);
 
-    let mut allocation = (0..sessions.len())
-        .map(|_| SessionAllocation {
-            assigned: Default::default(),
+    let num_levels = seeds.len();
+    let temperatures = (0..num_levels)
+        .map(|level| {
+            let fraction = (level as f32) / ((num_levels - 1) as f32);
+            tempering.min_temperature
+                * (tempering.max_temperature / tempering.min_temperature).powf(fraction)
         })
         .collect::<Vec<_>>();
 
-    let mut old_cost = solver.eval_allocation(&allocation).unwrap();
-    // println!("initial cost: {old_cost}");
-    let mut old_allocation = allocation.clone();
-
-    let total_steps = if !quick { 8000000 } else { 30000 };
-    let temp_multiplier = 1.5;
-    // let temp_multiplier = 1.0;
-
-    for i in 0..total_steps {
-        if let Some(_mutation) = solver.mutate_allocation(&mut allocation) {
-            let new_cost = solver.eval_allocation(&allocation);
-
-            let improved = match new_cost {
-                Some(new_cost) => {
-                    if new_cost <= old_cost {
-                        true
-                    } else {
-                        // possibly allow a bump, depending on temp
-                        let cost_increase = (new_cost - old_cost) as f32;
-                        let temperature =
-                            temp_multiplier * (total_steps as f32) / ((i as f32) + 1.0);
-                        // let temperature = 0.01
-                        //     * ((1.0 - ((i as f32) / (total_steps as f32))) * 1.5).powf(2.0)
-                        //     + 0.001;
-
-                        let accept_prob = (-cost_increase / temperature).exp();
-                        fastrand::f32() < accept_prob
-                        // false
+    let mut replicas = seeds
+        .iter()
+        .map(|&seed| {
+            let state = solver.initial_state();
+            Replica {
+                seed,
+                rng: Rng::with_seed(seed),
+                best_cost: state.cost,
+                best_allocations: state.allocations.clone(),
+                state,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // replica_at_level[level] is the index of the replica at temperatures[level]
+    let mut replica_at_level = (0..num_levels).collect::<Vec<_>>();
+    let mut exchange_rng = Rng::with_seed(seeds[0] ^ EXCHANGE_SEED_MIX);
+    let mut exchanges = 0;
+
+    let start = Instant::now();
+    let mut step = 0;
+    let mut steps_since_improvement = 0;
+    let mut best_cost = replicas[0].best_cost;
+
+    while !options.budget_exhausted(options.time_progress(start), step, steps_since_improvement) {
+        let mut level_of_replica = vec![0; num_levels];
+        for (level, &replica_index) in replica_at_level.iter().enumerate() {
+            level_of_replica[replica_index] = level;
+        }
+
+        replicas
+            .par_iter_mut()
+            .zip(level_of_replica.par_iter())
+            .for_each(|(replica, &level)| {
+                for _ in 0..tempering.exchange_interval {
+                    let accepted =
+                        solver.anneal_step(&mut replica.rng, &mut replica.state, options, || {
+                            temperatures[level]
+                        });
+                    if accepted && replica.state.cost < replica.best_cost {
+                        replica.best_cost = replica.state.cost;
+                        replica.best_allocations = replica.state.allocations.clone();
                     }
                 }
-                None => false,
-            };
+            });
 
-            if improved {
-                let unwrapped_new_cost = new_cost.unwrap();
-                old_allocation = allocation.clone();
-                // println!(
-                //     "{i}: improved by {} with {:?}, current cost: {}",
-                //     (old_cost as i64) - (unwrapped_new_cost as i64),
-                //     mutation,
-                //     unwrapped_new_cost
-                // );
-                old_cost = unwrapped_new_cost;
-            } else {
-                allocation = old_allocation.clone();
+        step += tempering.exchange_interval;
+        steps_since_improvement += tempering.exchange_interval;
+
+        let round_best = replicas
+            .iter()
+            .map(|replica| replica.best_cost)
+            .min()
+            .unwrap();
+        if round_best < best_cost {
+            best_cost = round_best;
+            steps_since_improvement = 0;
+        }
+
+        // alternate between exchanging (0, 1), (2, 3), ... and (1, 2), (3, 4), ...
+        let first_level = ((step / tempering.exchange_interval) % 2) as usize;
+        for level in (first_level..num_levels - 1).step_by(2) {
+            let colder = replica_at_level[level];
+            let hotter = replica_at_level[level + 1];
+
+            let beta_difference = 1.0 / temperatures[level] - 1.0 / temperatures[level + 1];
+            let energy_difference =
+                (replicas[colder].state.cost as f32) - (replicas[hotter].state.cost as f32);
+            if exchange_rng.f32() < (beta_difference * energy_difference).exp() {
+                replica_at_level.swap(level, level + 1);
+                exchanges += 1;
             }
         }
     }
 
-    (old_cost, old_allocation)
+    for replica in &replicas {
+        println!(
+            "seed = {}, cost = {}, best cost = {}",
+            replica.seed, replica.state.cost, replica.best_cost
+        );
+    }
+
+    let results = replicas
+        .into_iter()
+        .map(|replica| (replica.seed, replica.best_cost, replica.best_allocations))
+        .collect();
+    let (best_seed, best_cost, solution, alternatives) =
+        pick_alternatives(inputs, results, options);
+
+    println!(
+        "best_cost = {best_cost:?} found by seed {best_seed:?} after {step} steps per replica and {exchanges} exchanges (for {courses:?})"
+    );
+
+    (solution, best_seed, best_cost, alternatives)
 }
 
-pub fn solve_many_times(
-    seeds: Vec<Seed>,
-    course: Course,
-    applicants: &[Applicant],
-    sessions: &[Session],
-    desired_hours: &[(WeekNum, HourCount)],
-    quick: bool,
-) -> (Vec<SolvedSession>, Seed) {
-    let applicants = &applicants
-        .iter()
-        .filter(|applicant| applicant.course == course)
-        .cloned()
-        .collect::<Vec<_>>();
+// The non-zero terms making up the cost of a solution, and anything in it
+// which breaks the hard constraints
+pub fn evaluate_solution(
+    inputs: &SolveInputs,
+    solution: &[SolvedSession],
+) -> (Vec<CostItem>, Vec<Violation>) {
+    let solver = Solver::new(inputs);
+    let mut violations = vec![];
+    let allocations =
+        solver.allocations_from_solution(solution, |violation| violations.push(violation));
 
-    let best_seed = if seeds.len() > 1 {
-        seeds
-            .par_iter()
-            // .into_iter()
-            .min_by_key(|&seed| {
-                fastrand::seed(*seed);
-                let (cost, _) = solve(applicants, sessions, desired_hours, quick);
-                println!("seed = {seed}, cost = {cost}");
-                cost
-            })
-            .copied()
-            .unwrap()
-    } else if let Some(&seed) = seeds.first() {
-        seed
-    } else {
-        panic!("no seeds!")
+    let mut items = vec![];
+    solver.eval_allocation_terms(
+        &allocations,
+        |item| {
+            if item.cost > 0 {
+                items.push(item);
+            }
+        },
+        |violation| violations.push(violation),
+    );
+    (items, violations)
+}
+
+pub fn cost_breakdown(inputs: &SolveInputs, solution: &[SolvedSession]) -> Vec<CostItem> {
+    let (items, violations) = evaluate_solution(inputs, solution);
+    assert!(
+        violations.is_empty(),
+        "solution should be feasible, but has {violations:?}"
+    );
+    items
+}
+
+// A broken hard constraint, in a solution which didn't come from the solver
+#[derive(Debug, Clone)]
+pub enum Violation {
+    // anything in the solution's files which can't be matched up
+    UnknownSession {
+        description: String,
+    },
+    UnknownTutor {
+        session: usize,
+        zid: String,
+    },
+    Impossible {
+        session: usize,
+        applicant: usize,
+    },
+    Duplicate {
+        session: usize,
+        applicant: usize,
+    },
+    Clash {
+        session: usize,
+        other: usize,
+        applicant: usize,
+    },
+    Overfull {
+        session: usize,
+        num_tutors: usize,
+        max_tutors: usize,
+    },
+    WeeklyCap {
+        applicant: usize,
+        week: WeekNum,
+        hours: HourCount,
+        max_hours: HourCount,
+    },
+    DailyCap {
+        applicant: usize,
+        week: WeekNum,
+        day: Day,
+        hours: HourCount,
+        max_hours: HourCount,
+    },
+    TermCap {
+        applicant: usize,
+        hours: HourCount,
+        max_hours: HourCount,
+    },
+}
+
+// Something stopping a tutor from being added to (or removed from) a session
+#[derive(Debug, Clone, Copy)]
+pub enum Obstacle {
+    Impossible,
+    Pinned,
+    Full {
+        max_tutors: usize,
+    },
+    Clash {
+        session: usize,
+    },
+    WeeklyCap {
+        hours: HourCount,
+        max_hours: HourCount,
+    },
+    DailyCap {
+        hours: HourCount,
+        max_hours: HourCount,
+    },
+    TermCap {
+        hours: HourCount,
+        max_hours: HourCount,
+    },
+}
+
+pub struct Explanation {
+    pub assigned: bool,
+    pub availability: Availability,
+    pub obstacles: Vec<Obstacle>,
+    // how each cost term would change by adding or removing them, if nothing
+    // stops it
+    pub cost_changes: Vec<(CostTerm, i64)>,
+    // the effective hours of the session's course that week, and its target
+    pub week_hours: HourCount,
+    pub desired_hours: HourCount,
+    // anything in the solution which no longer fits the inputs
+    pub violations: Vec<Violation>,
+}
+
+// Why `applicant` is or isn't assigned to `session_index` in `solution`, by
+// trying to remove or add them
+pub fn explain(
+    inputs: &SolveInputs,
+    solution: &[SolvedSession],
+    session_index: usize,
+    applicant_index: usize,
+) -> Explanation {
+    let solver = Solver::new(inputs);
+    let mut violations = vec![];
+    let allocations =
+        solver.allocations_from_solution(solution, |violation| violations.push(violation));
+    solver.eval_allocation_terms(&allocations, |_| (), |violation| violations.push(violation));
+
+    let session = &solver.sessions[session_index];
+    let applicant = &solver.applicants[applicant_index];
+    let applicant_id = applicant_index as ApplicantId;
+    let length = session.length_hours as HourCount;
+    let assigned = allocations[session_index].assigned.contains(&applicant_id);
+    let availability = applicant.availabilities[session_index];
+
+    // totalled directly rather than through an `AllocationState`, since the
+    // solution may no longer be feasible
+    let applicant_hours = |in_total: &dyn Fn(usize) -> bool| {
+        (0..allocations.len())
+            .filter(|&other| in_total(other) && allocations[other].assigned.contains(&applicant_id))
+            .map(|other| solver.sessions[other].length_hours as HourCount)
+            .sum::<HourCount>()
     };
 
-    fastrand::seed(best_seed);
-    let (best_cost, solution) = solve(applicants, sessions, desired_hours, quick);
+    let mut obstacles = vec![];
+    if assigned {
+        if solver.pinned[session_index].contains(&applicant_id) {
+            obstacles.push(Obstacle::Pinned);
+        }
+    } else {
+        let weekly_hours = applicant_hours(&|other| {
+            solver.session_calendar_weeks[other] == solver.session_calendar_weeks[session_index]
+        });
+        let daily_hours = applicant_hours(&|other| {
+            solver.session_calendar_days[other] == solver.session_calendar_days[session_index]
+        });
+        let term_hours = applicant_hours(&|_| true);
 
-    println!("best_cost = {best_cost:?} with seed {best_seed:?} (for {course:?})");
-    // println!("solution = {solution:?}");
+        if availability == Availability::Impossible {
+            obstacles.push(Obstacle::Impossible);
+        }
+        if allocations[session_index].assigned.len() >= session.max_tutors() {
+            obstacles.push(Obstacle::Full {
+                max_tutors: session.max_tutors(),
+            });
+        }
+        for &other in &solver.session_conflicts[session_index] {
+            if allocations[other].assigned.contains(&applicant_id) {
+                obstacles.push(Obstacle::Clash { session: other });
+            }
+        }
+        if weekly_hours + length > applicant.max_hours_per_week {
+            obstacles.push(Obstacle::WeeklyCap {
+                hours: weekly_hours,
+                max_hours: applicant.max_hours_per_week,
+            });
+        }
+        if let Some(max_hours) = applicant.max_hours_per_day {
+            if daily_hours + length > max_hours {
+                obstacles.push(Obstacle::DailyCap {
+                    hours: daily_hours,
+                    max_hours,
+                });
+            }
+        }
+        if let Some(max_hours) = applicant.max_hours_per_term {
+            if term_hours + length > max_hours {
+                obstacles.push(Obstacle::TermCap {
+                    hours: term_hours,
+                    max_hours,
+                });
+            }
+        }
+    }
 
-    (
-        solution
+    let mut cost_changes = vec![];
+    if obstacles.is_empty() {
+        let mut changed = allocations.clone();
+        let changed_assigned = &mut changed[session_index].assigned;
+        if assigned {
+            changed_assigned.retain(|&mut other| other != applicant_id);
+        } else {
+            changed_assigned.push(applicant_id);
+        }
+
+        let term_totals = |allocations: &[SessionAllocation]| {
+            let mut totals = [0; CostTerm::ALL.len()];
+            // anything already broken in the solution stays broken
+            solver.eval_allocation_terms(
+                allocations,
+                |item| totals[item.term as usize] += item.cost as i64,
+                |_| (),
+            );
+            totals
+        };
+        let (before, after) = (term_totals(&allocations), term_totals(&changed));
+        cost_changes = CostTerm::ALL
             .into_iter()
-            .enumerate()
-            .map(|(session_index, allocation)| SolvedSession {
-                session: sessions[session_index].clone(),
-                applicants: allocation
-                    .assigned
-                    .into_iter()
-                    .map(|applicant_index| applicants[applicant_index as usize].clone())
-                    .collect(),
-            })
-            .collect::<Vec<_>>(),
-        best_seed,
+            .map(|term| (term, after[term as usize] - before[term as usize]))
+            .filter(|&(_, change)| change != 0)
+            .collect();
+    }
+
+    let week_index = solver.session_weeks[session_index];
+    let week_hours = (0..allocations.len())
+        .filter(|&other| solver.session_weeks[other] == week_index)
+        .map(|other| {
+            (solver.sessions[other].length_hours as HourCount)
+                * (allocations[other].assigned.len() as HourCount)
+        })
+        .sum();
+    Explanation {
+        assigned,
+        availability,
+        obstacles,
+        cost_changes,
+        week_hours,
+        desired_hours: solver.weeks[week_index].desired_total_hours,
+        violations,
+    }
+}
+
+// The pinned assignments, and those of `inputs.previous` which are still
+// feasible (warning about any which aren't)
+pub fn keepable_assignments(inputs: &SolveInputs) -> Vec<PinnedAssignment> {
+    let solver = Solver::new(inputs);
+    let state = solver.initial_state();
+    state
+        .allocations
+        .iter()
+        .enumerate()
+        .flat_map(|(session, allocation)| {
+            allocation
+                .assigned
+                .iter()
+                .map(move |&applicant| PinnedAssignment {
+                    session,
+                    applicant: applicant as usize,
+                })
+        })
+        .collect()
+}
+
+pub fn lower_bound(inputs: &SolveInputs) -> f64 {
+    solve_relaxation(inputs, false).objective
+}
+
+// Solves the integer program from `exact` directly. Its objective is a proven
+// lower bound, and the true cost of its allocation is an upper bound (the two
+// differ only by the terms which the integer program approximates).
+pub fn solve_exactly(courses: &[Course], inputs: &SolveInputs) -> (Vec<SolvedSession>, Cost, f64) {
+    let solver = Solver::new(inputs);
+
+    let exact_solution = solve_relaxation(inputs, true);
+    let allocations = exact_solution
+        .assigned
+        .into_iter()
+        .map(|assigned| SessionAllocation {
+            assigned: assigned
+                .into_iter()
+                .map(|applicant_index| applicant_index as ApplicantId)
+                .collect(),
+        })
+        .collect::<Vec<_>>();
+
+    let cost = solver
+        .eval_allocation(&allocations)
+        .expect("the integer program's solution should be feasible");
+
+    println!(
+        "exact cost = {cost} with lower bound {:.1} (for {courses:?})",
+        exact_solution.objective
+    );
+
+    (
+        to_solved_sessions(allocations, inputs.sessions, inputs.applicants),
+        cost,
+        exact_solution.objective,
     )
 }
@@ -51,6 +51,15 @@ impl<'a> Row<'a> {
         &self.tsv.rows[self.row_index][index]
     }
 
+    pub fn get_optional(&self, column: &str) -> Option<&'a str> {
+        let index = self
+            .tsv
+            .headers
+            .iter()
+            .position(|header| header == column)?;
+        Some(&self.tsv.rows[self.row_index][index])
+    }
+
     pub fn all_fields(&self) -> &[String] {
         &self.tsv.rows[self.row_index]
     }
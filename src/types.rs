@@ -1,6 +1,6 @@
-use std::str::FromStr;
+use std::{fmt, str::FromStr};
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Day {
     Mon,
     Tue,
@@ -78,6 +78,9 @@ impl FromStr for Venue {
     }
 }
 
+// The most tutors a session can have, unless it sets its own maximum
+pub const DEFAULT_MAX_ALLOCATION: usize = 5;
+
 #[derive(Debug, Clone)]
 pub struct Session {
     pub id: usize,
@@ -88,9 +91,36 @@ pub struct Session {
     pub length_hours: u8,
     pub location: String,
     pub min_allocation: Option<u16>,
+    pub max_allocation: Option<u16>,
+    pub course: Course,
+    // the same for every week of a recurring session (one line of sessions.txt)
+    pub slot: usize,
+}
+
+impl Session {
+    pub fn max_tutors(&self) -> usize {
+        self.max_allocation
+            .map_or(DEFAULT_MAX_ALLOCATION, |max_allocation| {
+                max_allocation as usize
+            })
+    }
+
+    // Whether one tutor can't do both sessions, because they overlap or (when
+    // one is f2f and the other online) are less than `commute_gap` hours apart
+    pub fn clashes(&self, other: &Session, commute_gap: u8) -> bool {
+        let gap = if self.venue == other.venue {
+            0
+        } else {
+            commute_gap
+        };
+        self.week == other.week
+            && self.day == other.day
+            && self.time_24hr < other.time_24hr + other.length_hours + gap
+            && other.time_24hr < self.time_24hr + self.length_hours + gap
+    }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, clap::ValueEnum)]
 pub enum Course {
     #[clap(name = "COMP1511")]
     Comp1511,
@@ -100,14 +130,13 @@ pub enum Course {
     Comp2521,
 }
 
-impl ToString for Course {
-    fn to_string(&self) -> String {
-        match self {
+impl fmt::Display for Course {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
             Course::Comp1511 => "COMP1511",
             Course::Comp1521 => "COMP1521",
             Course::Comp2521 => "COMP2521",
-        }
-        .into()
+        })
     }
 }
 
@@ -132,14 +161,34 @@ pub enum Availability {
     Preferred,
 }
 
+// How a tutor would like their sessions within a week arranged
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DayPreference {
+    // on as few days as possible
+    Cluster,
+    // at most one a day
+    Spread,
+}
+
 #[derive(Debug, Clone)]
 pub struct Applicant {
+    #[allow(dead_code)]
     pub id: u32,
+    #[allow(dead_code)]
     pub email: String,
     pub name: String,
     pub zid: String,
     pub course: Course,
+    // every course they can staff, primary course first
+    pub courses: Vec<Course>,
     pub max_hours_per_week: u16,
+    // roughly the middle of the range they asked for
+    pub requested_hours_per_week: u16,
     pub availabilities: Vec<Availability>,
     pub min_hours_per_week: Option<u16>,
+    // from their contract, if it limits their hours over the whole term
+    pub max_hours_per_term: Option<u16>,
+    pub min_hours_per_term: Option<u16>,
+    pub max_hours_per_day: Option<u16>,
+    pub day_preference: Option<DayPreference>,
 }